@@ -0,0 +1,170 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Macros to implement the traits of the `overseer` crate with less boilerplate.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+	parse_macro_input, Data, DeriveInput, Error, FnArg, GenericArgument, ImplItem, ImplItemMethod,
+	ItemImpl, PathArguments, Type,
+};
+
+/// Implement `Subsystem` for a type with an `async fn run` in the annotated `impl` block.
+///
+/// `run` takes the `Subsystem` by value and its `SubsystemContext`, and returns
+/// a `SubsystemResult<()>`. The message type of the `Subsystem` is the one of
+/// the `SubsystemContext`. The type has to be `Clone`, as `run` is given a clone
+/// of it each time the `Subsystem` is (re)started.
+///
+/// ```ignore
+/// #[derive(Clone)]
+/// struct ValidationSubsystem;
+///
+/// #[overseer_derive::subsystem]
+/// impl ValidationSubsystem {
+///     async fn run(self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SubsystemResult<()> {
+///         while let FromOverseer::Communication { msg } = ctx.recv().await? {
+///             println!("received {:?}", msg);
+///         }
+///
+///         ctx.conclude()
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn subsystem(attr: TokenStream, item: TokenStream) -> TokenStream {
+	if !attr.is_empty() {
+		return Error::new(Span::call_site(), "`subsystem` takes no arguments")
+			.to_compile_error()
+			.into();
+	}
+
+	let item = parse_macro_input!(item as ItemImpl);
+
+	match impl_subsystem(&item) {
+		Ok(subsystem) => quote!(#item #subsystem).into(),
+		Err(e) => {
+			let e = e.to_compile_error();
+			quote!(#item #e).into()
+		}
+	}
+}
+
+fn impl_subsystem(item: &ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+	if let Some((_, path, _)) = &item.trait_ {
+		return Err(Error::new_spanned(path, "`subsystem` is to be put on an inherent `impl` block"));
+	}
+
+	let run = item.items.iter()
+		.find_map(|item| match item {
+			ImplItem::Method(method) if method.sig.ident == "run" => Some(method),
+			_ => None,
+		})
+		.ok_or_else(|| Error::new_spanned(&item.self_ty, "no `run` method to start the `Subsystem` with"))?;
+
+	if run.sig.asyncness.is_none() {
+		return Err(Error::new_spanned(&run.sig, "`run` has to be an `async fn`"));
+	}
+
+	let message = message_type(run)?;
+	let self_ty = &item.self_ty;
+	let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+
+	Ok(quote! {
+		impl #impl_generics ::overseer::Subsystem<#message> for #self_ty #where_clause {
+			fn start(
+				&mut self,
+				ctx: ::overseer::SubsystemContext<#message>,
+			) -> ::overseer::SpawnedSubsystem {
+				let this = ::std::clone::Clone::clone(self);
+				::overseer::SpawnedSubsystem(::std::boxed::Box::pin(this.run(ctx)))
+			}
+		}
+	})
+}
+
+// The `M` of the `SubsystemContext<M>` that `run` takes besides `self`.
+fn message_type(run: &ImplItemMethod) -> syn::Result<&Type> {
+	let mut inputs = run.sig.inputs.iter();
+
+	match inputs.next() {
+		Some(FnArg::Receiver(receiver)) if receiver.reference.is_none() => (),
+		_ => return Err(Error::new_spanned(&run.sig, "`run` has to take `self` by value")),
+	}
+
+	let ctx = match (inputs.next(), inputs.next()) {
+		(Some(FnArg::Typed(ctx)), None) => ctx,
+		_ => return Err(Error::new_spanned(&run.sig.inputs, "`run` has to take only a `SubsystemContext` besides `self`")),
+	};
+
+	let segment = match &*ctx.ty {
+		Type::Path(ty) => ty.path.segments.last(),
+		_ => None,
+	};
+
+	match segment {
+		Some(segment) if segment.ident == "SubsystemContext" => match &segment.arguments {
+			PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+				GenericArgument::Type(message) => Ok(message),
+				_ => Err(Error::new_spanned(args, "expected the message type of the `SubsystemContext`")),
+			},
+			_ => Err(Error::new_spanned(segment, "expected the message type of the `SubsystemContext`")),
+		},
+		_ => Err(Error::new_spanned(&ctx.ty, "expected a `SubsystemContext<M>`")),
+	}
+}
+
+/// Derive `MessageKind` for an enum, the kind of a message is the name of its variant.
+///
+/// ```ignore
+/// #[derive(Debug, overseer_derive::MessageKind)]
+/// enum NetworkMessage {
+///     Connected(PeerId),
+///     Disconnected { peer: PeerId },
+/// }
+///
+/// assert_eq!(NetworkMessage::Connected(peer).kind(), "Connected");
+/// ```
+#[proc_macro_derive(MessageKind)]
+pub fn message_kind(item: TokenStream) -> TokenStream {
+	let item = parse_macro_input!(item as DeriveInput);
+
+	let data = match &item.data {
+		Data::Enum(data) => data,
+		_ => {
+			return Error::new_spanned(&item.ident, "`MessageKind` can only be derived for an enum")
+				.to_compile_error()
+				.into();
+		}
+	};
+
+	let variants = data.variants.iter().map(|variant| &variant.ident);
+	let names = data.variants.iter().map(|variant| variant.ident.to_string());
+	let ident = &item.ident;
+	let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+
+	quote!(
+		impl #impl_generics ::overseer::MessageKind for #ident #ty_generics #where_clause {
+			fn kind(&self) -> &'static str {
+				match *self {
+					#(Self::#variants { .. } => #names,)*
+				}
+			}
+		}
+	).into()
+}
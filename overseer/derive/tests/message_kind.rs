@@ -0,0 +1,33 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use overseer::MessageKind;
+
+#[allow(dead_code)]
+#[derive(Debug, overseer_derive::MessageKind)]
+enum TestMessage<T> {
+	Unit,
+	Tuple(T, usize),
+	Struct { value: T },
+}
+
+// Checks that the derived kind is the name of the variant, whatever its fields.
+#[test]
+fn kind_is_variant_name() {
+	assert_eq!(TestMessage::<u8>::Unit.kind(), "Unit");
+	assert_eq!(TestMessage::Tuple(1u8, 2).kind(), "Tuple");
+	assert_eq!(TestMessage::Struct { value: 1u8 }.kind(), "Struct");
+}
@@ -0,0 +1,82 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+use futures::{channel::mpsc, executor, pin_mut, select, FutureExt, StreamExt};
+use overseer::{
+	AllMessages, CandidateBackingSubsystemMessage, FromOverseer, Overseer, SubsystemContext,
+	ShutdownReason, SubsystemResult, ValidationSubsystemMessage,
+};
+use overseer_derive::subsystem;
+
+// Reports the number of messages it has received so far, starting over on each start.
+#[derive(Clone)]
+struct Counter {
+	received: usize,
+	tx: mpsc::UnboundedSender<usize>,
+}
+
+#[subsystem]
+impl Counter {
+	async fn run(mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SubsystemResult<()> {
+		while let FromOverseer::Communication { .. } = ctx.recv().await? {
+			self.received += 1;
+			let _ = self.tx.unbounded_send(self.received);
+		}
+
+		ctx.conclude()
+	}
+}
+
+#[derive(Clone)]
+struct Idle;
+
+#[subsystem]
+impl Idle {
+	async fn run(self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SubsystemResult<()> {
+		while ctx.recv().await.is_ok() {}
+
+		Ok(())
+	}
+}
+
+// Checks that the generated `Subsystem` runs `run` and receives its messages.
+#[test]
+fn subsystem_runs() {
+	let spawner = executor::ThreadPool::new().unwrap();
+
+	executor::block_on(async move {
+		let (tx, mut rx) = mpsc::unbounded();
+		let (overseer, mut handler) = Overseer::new(
+			Box::new(Counter { received: 0, tx }),
+			Box::new(Idle),
+			spawner,
+		).unwrap();
+		let overseer_fut = overseer.fuse();
+		pin_mut!(overseer_fut);
+
+		for expected in 1..=3 {
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				received = rx.next() => assert_eq!(received, Some(expected)),
+			}
+		}
+
+		handler.stop().await.unwrap();
+		assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+	})
+}
@@ -20,7 +20,7 @@
 
 use std::time::Duration;
 use futures::{
-	pending, pin_mut, executor, select, stream,
+	pin_mut, executor, select, stream,
 	FutureExt, StreamExt,
 };
 use futures_timer::Delay;
@@ -36,21 +36,24 @@ struct Subsystem1;
 impl Subsystem1 {
 	async fn run(mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>)  {
 		loop {
-			match ctx.try_recv().await {
-				Ok(Some(msg)) => {
-					if let FromOverseer::Communication { msg } = msg {
-						log::info!("msg {:?}", msg);
-					}
-					continue;
-				}
-				Ok(None) => (),
-				Err(_) => {
-					log::info!("exiting");
-					return;
+			let mut delay = Delay::new(Duration::from_secs(1)).fuse();
+			let mut messages = ctx.recv_stream();
+
+			loop {
+				select! {
+					msg = messages.next() => match msg {
+						Some(FromOverseer::Communication { msg }) => log::info!("msg {:?}", msg),
+						Some(FromOverseer::Signal(_)) => (),
+						None => {
+							log::info!("exiting");
+							return;
+						}
+					},
+					_ = delay => break,
 				}
 			}
+			drop(messages);
 
-			Delay::new(Duration::from_secs(1)).await;
 			ctx.send_msg(AllMessages::Validation(
 				ValidationSubsystemMessage::ValidityAttestation
 			)).await.unwrap();
@@ -62,6 +65,8 @@ impl Subsystem<CandidateBackingSubsystemMessage> for Subsystem1 {
 	fn start(&mut self, ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
 		SpawnedSubsystem(Box::pin(async move {
 			Self::run(ctx).await;
+
+			Ok(())
 		}))
 	}
 }
@@ -77,19 +82,12 @@ impl Subsystem2 {
 			}
 		})).await.unwrap();
 
-		loop {
-			match ctx.try_recv().await {
-				Ok(Some(msg)) => {
-					log::info!("Subsystem2 received message {:?}", msg);
-					continue;
-				}
-				Ok(None) => { pending!(); }
-				Err(_) => {
-					log::info!("exiting");
-					return;
-				},
-			}
+		let mut messages = ctx.recv_stream();
+		while let Some(msg) = messages.next().await {
+			log::info!("Subsystem2 received message {:?}", msg);
 		}
+
+		log::info!("exiting");
 	}
 }
 
@@ -97,6 +95,8 @@ impl Subsystem<ValidationSubsystemMessage> for Subsystem2 {
 	fn start(&mut self, ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
 		SpawnedSubsystem(Box::pin(async move {
 			Self::run(ctx).await;
+
+			Ok(())
 		}))
 	}
 }
@@ -54,715 +54,9514 @@
 //!             ..................................................................
 //! ```
 
-use std::fmt::Debug;
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Write};
+use std::panic::AssertUnwindSafe;
 use std::pin::Pin;
-use std::task::Poll;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, PoisonError, atomic::{AtomicBool, AtomicU64, Ordering}};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use futures::channel::{mpsc, oneshot};
 use futures::{
-	pending, poll, select,
-	future::{BoxFuture, RemoteHandle},
-	stream::FuturesUnordered,
-	task::{Spawn, SpawnError, SpawnExt},
+	poll, select,
+	executor::ThreadPool,
+	future::{self, AbortHandle, BoxFuture, Either, FusedFuture, Shared},
+	stream::{self, BoxStream, FusedStream, FuturesUnordered},
+	task::{AtomicWaker, FutureObj, Spawn, SpawnError, SpawnExt},
 	Future, FutureExt, SinkExt, StreamExt,
 };
 use futures_timer::Delay;
 use streamunordered::{StreamYield, StreamUnordered};
 
+#[cfg(feature = "record")]
+use serde::{Serialize, Deserialize};
+
+#[cfg(feature = "proxy")]
+pub mod proxy;
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
 /// An error type that describes faults that may happen
 ///
 /// These are:
 ///   * Channels being closed
+///   * Channels being full
 ///   * Subsystems dying when they are not expected to
-///   * Subsystems not dying when they are told to die
-///   * etc.
+///   * Failures to spawn tasks on the executor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubsystemError {
+	/// The other side of a channel has been dropped.
+	ChannelClosed,
+	/// A channel has reached its capacity.
+	MailboxFull,
+	/// The `Subsystem` with this id has stopped while it was expected to keep running.
+	SubsystemStopped(SubsystemId),
+	/// No `Subsystem` has been run in the place of this id.
+	UnknownSubsystem(SubsystemId),
+	/// A `Subsystem` has panicked.
+	SubsystemPanicked,
+	/// The executor has failed to spawn a task.
+	SpawnFailed,
+	/// The dependencies between `Subsystem`s form a cycle.
+	DependencyCycle,
+	/// The dependencies of a `Subsystem` have not become ready in time.
+	DependencyTimeout,
+	/// A `Subsystem` has been stopped for not sending heartbeats.
+	Unresponsive,
+	/// The `Overseer` has not answered a request in time.
+	Timeout,
+	/// A `Subsystem` is already running in the place of the one to be spawned.
+	AlreadyRunning,
+	/// The `Overseer` is draining and takes no more messages from the outside world.
+	Draining,
+}
+
+impl std::fmt::Display for SubsystemError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SubsystemError::ChannelClosed => write!(f, "channel closed"),
+			SubsystemError::MailboxFull => write!(f, "mailbox full"),
+			SubsystemError::SubsystemStopped(id) => write!(f, "subsystem {:?} stopped unexpectedly", id),
+			SubsystemError::UnknownSubsystem(id) => write!(f, "no subsystem {:?} has been run", id),
+			SubsystemError::SubsystemPanicked => write!(f, "subsystem panicked"),
+			SubsystemError::SpawnFailed => write!(f, "failed to spawn a task"),
+			SubsystemError::DependencyCycle => write!(f, "subsystem dependencies form a cycle"),
+			SubsystemError::DependencyTimeout => write!(f, "subsystem dependencies not ready in time"),
+			SubsystemError::Unresponsive => write!(f, "subsystem stopped sending heartbeats"),
+			SubsystemError::Timeout => write!(f, "overseer did not answer in time"),
+			SubsystemError::AlreadyRunning => write!(f, "subsystem already running"),
+			SubsystemError::Draining => write!(f, "overseer is draining"),
+		}
+	}
+}
+
+impl std::error::Error for SubsystemError {}
+
+/// An error returned by [`SubsystemContext::try_send_msg`] and [`OverseerHandler::try_send_msg`],
+/// handing back the message.
+///
+/// [`SubsystemContext::try_send_msg`]: struct.SubsystemContext.html#method.try_send_msg
+/// [`OverseerHandler::try_send_msg`]: struct.OverseerHandler.html#method.try_send_msg
 #[derive(Debug)]
-pub struct SubsystemError;
+pub struct TrySendError {
+	/// Why the message was not sent, `MailboxFull`, `ChannelClosed` or `Draining`.
+	pub error: SubsystemError,
+	/// The message that was not sent.
+	pub msg: AllMessages,
+}
+
+impl std::fmt::Display for TrySendError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "failed to send a message: {}", self.error)
+	}
+}
+
+impl std::error::Error for TrySendError {}
 
 impl From<mpsc::SendError> for SubsystemError {
-	fn from(_: mpsc::SendError) -> Self {
-		Self
+	fn from(e: mpsc::SendError) -> Self {
+		if e.is_full() {
+			SubsystemError::MailboxFull
+		} else {
+			SubsystemError::ChannelClosed
+		}
+	}
+}
+
+impl<T> From<mpsc::TrySendError<T>> for SubsystemError {
+	fn from(e: mpsc::TrySendError<T>) -> Self {
+		e.into_send_error().into()
 	}
 }
 
 impl From<oneshot::Canceled> for SubsystemError {
 	fn from(_: oneshot::Canceled) -> Self {
-		Self
+		SubsystemError::ChannelClosed
 	}
 }
 
 impl From<SpawnError> for SubsystemError {
-    fn from(_: SpawnError) -> Self {
-		Self
-    }
+	fn from(_: SpawnError) -> Self {
+		SubsystemError::SpawnFailed
+	}
 }
 
 /// A `Result` type that wraps [`SubsystemError`].
 ///
-/// [`SubsystemError`]: struct.SubsystemError.html
+/// [`SubsystemError`]: enum.SubsystemError.html
 pub type SubsystemResult<T> = Result<T, SubsystemError>;
 
 /// An asynchronous subsystem task that runs inside and being overseen by the [`Overseer`].
 ///
 /// In essence it's just a newtype wrapping a `BoxFuture`.
 ///
+/// A subsystem resolving to `Ok(())` is considered to have concluded its
+/// work intentionally, while an `Err` is treated as a failure of the subsystem.
+///
 /// [`Overseer`]: struct.Overseer.html
-pub struct SpawnedSubsystem(pub BoxFuture<'static, ()>);
+pub struct SpawnedSubsystem(pub BoxFuture<'static, SubsystemResult<()>>);
 
 // A capacity of bounded channels inside the overseer.
 const CHANNEL_CAPACITY: usize = 1024;
 // A graceful `Overseer` teardown time delay.
 const STOP_DELAY: u64 = 1;
+// How long the subsystems get to drain before they are stopped, in seconds.
+const DRAIN_TIMEOUT: u64 = 30;
 
-/// A type of messages that are sent from [`Subsystem`] to [`Overseer`].
+/// What the [`Overseer`] does when one of the [`Subsystem`]s fails.
 ///
-/// It wraps a system-wide [`AllMessages`] type that represents all possible
-/// messages in the system.
+/// A [`Subsystem`] fails when its [`SpawnedSubsystem`] future resolves to an `Err`.
 ///
-/// [`AllMessages`]: enum.AllMessages.html
-/// [`Subsystem`]: trait.Subsystem.html
 /// [`Overseer`]: struct.Overseer.html
-enum ToOverseer {
-	/// This is a message sent by a `Subsystem`.
-	SubsystemMessage(AllMessages),
-
-	/// A message that wraps something the `Subsystem` is desiring to
-	/// spawn on the overseer and a `oneshot::Sender` to signal the result
-	/// of the spawn.
-	SpawnJob {
-		s: BoxFuture<'static, ()>,
-		res: oneshot::Sender<SubsystemResult<()>>,
-	},
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`SpawnedSubsystem`]: struct.SpawnedSubsystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExitPolicy {
+	/// Stop the `Overseer` and all other `Subsystem`s with an error.
+	#[default]
+	Panic,
+	/// Start the `Subsystem` again with a fresh `SubsystemContext`.
+	Restart,
+	/// Log the failure and keep running without the `Subsystem`.
+	Ignore,
 }
 
-/// Some event from outer world.
-enum Event {
-	BlockImport,
-	BlockFinalized,
-	MsgToSubsystem(AllMessages),
-	Stop,
+/// Which other [`Subsystem`]s the [`Overseer`] restarts along with a failed one.
+///
+/// The `Subsystem`s to restart are stopped, and started again once they have concluded.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SupervisionStrategy {
+	/// Restart only the failed `Subsystem`.
+	#[default]
+	OneForOne,
+	/// Restart all the `Subsystem`s.
+	OneForAll,
+	/// Restart the failed `Subsystem` and those started after it.
+	RestForOne,
 }
 
-/// Some message that is sent from one of the `Subsystem`s to the outside world.
-pub enum OutboundMessage {
-	SubsystemMessage {
-		msg: AllMessages,
-	}
+/// What the [`Overseer`] does with a message to a [`Subsystem`] whose channel and
+/// overflow are both full.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MailboxPolicy {
+	/// Wait for the `Subsystem` to make room for the message.
+	///
+	/// No other message is dispatched meanwhile, so a slow `Subsystem` holds up all the others.
+	Wait,
+	/// Hand the message over to the [`OverseerConfig::dead_letters`] instead,
+	/// or drop it if there are none.
+	///
+	/// [`OverseerConfig::dead_letters`]: struct.OverseerConfig.html#structfield.dead_letters
+	#[default]
+	DeadLetter,
 }
 
-/// A handler used to communicate with the [`Overseer`].
+/// A limit on how fast a [`Subsystem`] may send messages, enforced by the [`Overseer`].
 ///
+/// The `Subsystem` has a bucket of up to `burst` tokens, refilled at `messages_per_second`.
+/// Each message it sends takes a token, so it may send `burst` messages at once after
+/// having been quiet, and `messages_per_second` in the long run. A rate or a burst of `0`
+/// is taken as `1`.
+///
+/// [`Subsystem`]: trait.Subsystem.html
 /// [`Overseer`]: struct.Overseer.html
-pub struct OverseerHandler {
-	events_tx: mpsc::Sender<Event>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+	/// How many messages the `Subsystem` may send per second.
+	pub messages_per_second: u32,
+	/// How many messages the `Subsystem` may send at once.
+	pub burst: u32,
+	/// What to do with the messages sent past the limit.
+	pub policy: RateLimitPolicy,
 }
 
-impl OverseerHandler {
-	/// Inform the `Overseer` that that some block was imported.
-	pub async fn block_imported(&mut self) -> SubsystemResult<()> {
-		self.events_tx.send(Event::BlockImport).await?;
-
-		Ok(())
-	}
-
-	/// Send some message to one of the `Subsystem`s.
-	pub async fn send_msg(&mut self, msg: AllMessages) -> SubsystemResult<()> {
-		self.events_tx.send(Event::MsgToSubsystem(msg)).await?;
-
-		Ok(())
-	}
+/// What the [`Overseer`] does with a message from a [`Subsystem`] that is over its [`RateLimit`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`RateLimit`]: struct.RateLimit.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RateLimitPolicy {
+	/// Keep the message until the limit allows for it to be dispatched.
+	///
+	/// The messages kept past the [`OverseerConfig::overflow_capacity`] are dropped.
+	///
+	/// [`OverseerConfig::overflow_capacity`]: struct.OverseerConfig.html#structfield.overflow_capacity
+	#[default]
+	Delay,
+	/// Drop the message.
+	Drop,
+}
 
-	/// Inform the `Overseer` that that some block was finalized.
-	pub async fn block_finalized(&mut self) -> SubsystemResult<()> {
-		self.events_tx.send(Event::BlockFinalized).await?;
+/// How the [`Overseer`] restarts failed [`Subsystem`]s.
+///
+/// A failed `Subsystem` is started again after a delay of `base_delay * 2^attempts`
+/// capped at `max_delay`. Once a `Subsystem` has been restarted `max_retries` times
+/// the [`ExitPolicy`] is applied to any further failure.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`ExitPolicy`]: enum.ExitPolicy.html
+#[derive(Debug, Clone)]
+pub struct RestartPolicy {
+	/// How many times a `Subsystem` may be restarted.
+	pub max_retries: usize,
+	/// The delay before the first restart.
+	pub base_delay: Duration,
+	/// The upper bound of the delay between restarts.
+	pub max_delay: Duration,
+}
 
-		Ok(())
+impl RestartPolicy {
+	// The delay before restarting a subsystem that has already been restarted `attempts` times.
+	fn delay(&self, attempts: usize) -> Duration {
+		2u32.checked_pow(attempts as u32)
+			.and_then(|factor| self.base_delay.checked_mul(factor))
+			.map_or(self.max_delay, |delay| delay.min(self.max_delay))
 	}
+}
 
-	/// Tell `Overseer` to shutdown.
-	pub async fn stop(&mut self) -> SubsystemResult<()> {
-		self.events_tx.send(Event::Stop).await?;
-
-		Ok(())
-	}
+/// Capacities of the channels between the [`Overseer`] and a [`Subsystem`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone)]
+pub struct ChannelConfig {
+	/// Capacity of the channel carrying messages to the `Subsystem`.
+	pub inbound: usize,
+	/// Capacity of the channel carrying messages from the `Subsystem`.
+	pub outbound: usize,
+	/// Make the channel carrying messages to the `Subsystem` unbounded.
+	///
+	/// The `Overseer` never waits on an unbounded channel, which is useful for
+	/// `Subsystem`s that must never block the `Overseer`. `inbound` is ignored then.
+	pub unbounded_inbound: bool,
+	/// How many messages may wait in the mailbox of the `Subsystem` before it is lagging.
+	///
+	/// Once its mailbox has grown to this many messages the `Overseer` logs a warning,
+	/// emits `OverseerEvent::SubsystemLagging` and counts it in the metrics, and it does so
+	/// again only after the mailbox has shrunk below this. The mailbox includes the overflow,
+	/// so this may be larger than `inbound`. If `None` the `Subsystem` is not checked.
+	pub high_water_mark: Option<usize>,
 }
 
-impl Debug for ToOverseer {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-		match self {
-			ToOverseer::SubsystemMessage(msg) => {
-				write!(f, "OverseerMessage::SubsystemMessage({:?})", msg)
-			}
-			ToOverseer::SpawnJob { .. } => write!(f, "OverseerMessage::Spawn(..)")
+impl Default for ChannelConfig {
+	fn default() -> Self {
+		Self {
+			inbound: CHANNEL_CAPACITY,
+			outbound: CHANNEL_CAPACITY,
+			unbounded_inbound: false,
+			high_water_mark: None,
 		}
 	}
 }
 
-/// A running instance of some [`Subsystem`].
+/// The default of [`OverseerConfig::messages_per_pass`].
+///
+/// [`OverseerConfig::messages_per_pass`]: struct.OverseerConfig.html#structfield.messages_per_pass
+const MESSAGES_PER_PASS: usize = 16;
+
+/// The weight of a [`Subsystem`] that is not given one in [`OverseerConfig::weights`].
 ///
 /// [`Subsystem`]: trait.Subsystem.html
-struct SubsystemInstance<M: Debug> {
-	tx: mpsc::Sender<FromOverseer<M>>,
-}
+/// [`OverseerConfig::weights`]: struct.OverseerConfig.html#structfield.weights
+const DEFAULT_WEIGHT: u32 = 1;
 
-/// A context type that is given to the [`Subsystem`] upon spawning.
-/// It can be used by [`Subsystem`] to communicate with other [`Subsystem`]s
-/// or to spawn it's [`SubsystemJob`]s.
+/// How many messages of a higher priority are dispatched before a waiting message of a lower one.
+const MAX_PRIORITY_STREAK: usize = 8;
+
+/// How many messages are taken from the [`Subsystem`]s in a pass at most, whatever their shares.
 ///
-/// [`Overseer`]: struct.Overseer.html
 /// [`Subsystem`]: trait.Subsystem.html
-/// [`SubsystemJob`]: trait.SubsystemJob.html
-pub struct SubsystemContext<M: Debug>{
-	rx: mpsc::Receiver<FromOverseer<M>>,
-	tx: mpsc::Sender<ToOverseer>,
-}
+const MAX_MESSAGES_PER_PASS: usize = 1024;
 
-/// A signal used by [`Overseer`] to communicate with the [`Subsystem`]s.
+/// The priority of a message sent by a [`Subsystem`].
+///
+/// Out of the messages that the [`Overseer`] has received at the same time those
+/// of a higher priority are dispatched first. Hence a message may overtake the
+/// messages of a lower priority sent before it by the same `Subsystem`.
 ///
 /// [`Overseer`]: struct.Overseer.html
 /// [`Subsystem`]: trait.Subsystem.html
-#[derive(Debug)]
-pub enum OverseerSignal {
-	/// `Subsystem` should start working.
-	StartWork,
-	/// `Subsystem` should stop working.
-	StopWork,
-	/// Conclude the work of the `Overseer` and all `Subsystem`s.
-	Conclude,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+	/// Dispatched before all other messages.
+	High,
+	/// The priority of the messages sent with `send_msg`.
+	#[default]
+	Normal,
+	/// Dispatched after all other messages.
+	Low,
 }
 
-#[derive(Debug)]
-/// A message type used by the Validation [`Subsystem`].
+/// Runs blocking work, such as disk IO, apart from the executor of the [`Overseer`].
 ///
-/// [`Subsystem`]: trait.Subsystem.html
-pub enum ValidationSubsystemMessage {
-	ValidityAttestation,
+/// [`Overseer`]: struct.Overseer.html
+pub trait SpawnBlocking: Debug + Send + Sync {
+	/// Run `f` to completion on a thread where it is free to block.
+	fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>);
 }
 
-#[derive(Debug)]
-/// A message type used by the CandidateBacking [`Subsystem`].
-///
-/// [`Subsystem`]: trait.Subsystem.html
-pub enum CandidateBackingSubsystemMessage {
-	RegisterBackingWatcher,
-	Second,
+impl SpawnBlocking for ThreadPool {
+	fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+		self.spawn_ok(async move { f() });
+	}
 }
 
-/// A message type tying together all message types that are used across [`Subsystem`]s.
+/// Runs blocking work on a `ThreadPool` that is created along with the first piece of it.
 ///
-/// [`Subsystem`]: trait.Subsystem.html
-#[derive(Debug)]
-pub enum AllMessages {
-	Validation(ValidationSubsystemMessage),
-	CandidateBacking(CandidateBackingSubsystemMessage),
+/// The [`Overseer`] runs the blocking work on it unless given another [`SpawnBlocking`],
+/// so no threads are started for blocking work before there is any, and no more than
+/// the `ThreadPool` has once there is.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`SpawnBlocking`]: trait.SpawnBlocking.html
+#[derive(Debug, Default)]
+pub struct LazyThreadPool {
+	pool: Mutex<Option<ThreadPool>>,
 }
 
-/// A message type that a [`Subsystem`] receives from the [`Overseer`].
-/// It wraps siglans from the [`Overseer`] and messages that are circulating
-/// between subsystems.
+impl SpawnBlocking for LazyThreadPool {
+	fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+		let mut pool = self.pool.lock().unwrap_or_else(PoisonError::into_inner);
+
+		if pool.is_none() {
+			match ThreadPool::builder().name_prefix("overseer-blocking-").create() {
+				Ok(created) => *pool = Some(created),
+				// A job that has no thread to run on is dropped, which fails the one waiting for it.
+				Err(e) => {
+					log::warn!("Failed to start a thread pool for blocking work {:?}", e);
+					return;
+				}
+			}
+		}
+
+		if let Some(pool) = pool.as_ref() {
+			pool.spawn_blocking(f);
+		}
+	}
+}
+
+/// The source of time of the [`Overseer`] and its [`Subsystem`]s.
 ///
-/// It is generic over over the message type `M` that a particular `Subsystem` may use.
+/// Everything the `Overseer` times, such as restart backoffs, heartbeat deadlines and
+/// stop timeouts, is timed with it. A test can hence replace it to control time.
 ///
 /// [`Overseer`]: struct.Overseer.html
 /// [`Subsystem`]: trait.Subsystem.html
-#[derive(Debug)]
-pub enum FromOverseer<M: Debug> {
-	/// Signal from the `Overseer`.
-	Signal(OverseerSignal),
+pub trait Clock: Debug + Send + Sync {
+	/// The current time.
+	fn now(&self) -> Instant;
 
-	/// Some other `Subsystem`'s message.
-	Communication {
-		msg: M,
-	},
+	/// Wait until `duration` has passed.
+	fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
 }
 
-impl<M: Debug> SubsystemContext<M> {
-	/// Try to asyncronously receive a message.
-	///
-	/// This has to be used with caution, if you loop over this without
-	/// using `pending!()` macro you will end up with a busy loop!
-	pub async fn try_recv(&mut self) -> Result<Option<FromOverseer<M>>, ()> {
-		match poll!(self.rx.next()) {
-			Poll::Ready(Some(msg)) => Ok(Some(msg)),
-			Poll::Ready(None) => Err(()),
-			Poll::Pending => Ok(None),
-		}
+/// The [`Clock`] of the system, which the [`Overseer`] uses unless given another one.
+///
+/// [`Clock`]: trait.Clock.html
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
 	}
 
-	/// Receive a message.
-	pub async fn recv(&mut self) -> SubsystemResult<FromOverseer<M>> {
-		self.rx.next().await.ok_or(SubsystemError)
+	fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+		Delay::new(duration).boxed()
 	}
+}
 
-	/// Spawn a child task on the executor.
-	pub async fn spawn(&mut self, s: Pin<Box<dyn Future<Output = ()> + Send>>) -> SubsystemResult<()> {
-		let (tx, rx) = oneshot::channel();
-		self.tx.send(ToOverseer::SpawnJob {
-			s,
-			res: tx,
-		}).await?;
+// The executor of the `Overseer` as handed out to its `Subsystem`s.
+#[derive(Clone)]
+struct SharedSpawner(Arc<dyn Spawn + Send + Sync>);
 
-		rx.await?
+impl Spawn for SharedSpawner {
+	fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+		self.0.spawn_obj(future)
 	}
 
-	/// Send a direct message to some other `Subsystem`, routed based on message type.
-	pub async fn send_msg(&mut self, msg: AllMessages) -> SubsystemResult<()> {
-		self.tx.send(ToOverseer::SubsystemMessage(msg)).await?;
+	fn status(&self) -> Result<(), SpawnError> {
+		self.0.status()
+	}
+}
 
-		Ok(())
+/// Configuration of the [`Overseer`].
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone)]
+pub struct OverseerConfig {
+	/// What to do when a `Subsystem` fails.
+	pub exit_policy: ExitPolicy,
+	/// Restart failed `Subsystem`s with a backoff before applying the `exit_policy`.
+	pub restart_policy: Option<RestartPolicy>,
+	/// Which other `Subsystem`s to restart when a failed one is restarted.
+	pub supervision: SupervisionStrategy,
+	/// Channels of the validation `Subsystem`.
+	pub validation_channels: ChannelConfig,
+	/// Channels of the candidate backing `Subsystem`.
+	pub candidate_backing_channels: ChannelConfig,
+	/// How long to wait for the `Subsystem`s to conclude when stopping.
+	pub stop_grace_period: Duration,
+	/// How long the `Subsystem`s get to process their pending messages and conclude when draining.
+	///
+	/// Once it has passed the `Overseer` stops as if it had been asked to, and drops the
+	/// `Subsystem`s that have not concluded within the `stop_grace_period` after that.
+	pub drain_timeout: Duration,
+	/// Capacity of the channel carrying the messages and requests of the [`OverseerHandler`]s.
+	///
+	/// The `Overseer` takes from it in turns with the messages of the `Subsystem`s, so
+	/// the handlers are slowed down to the pace of the `Overseer` rather than queuing up
+	/// messages without bound.
+	///
+	/// [`OverseerHandler`]: struct.OverseerHandler.html
+	pub events_capacity: usize,
+	/// How many messages to a `Subsystem` are kept once its channel is full.
+	///
+	/// The `Overseer` goes on dispatching the messages to the other `Subsystem`s
+	/// meanwhile, it only applies the `mailbox_policy` once the overflow is full too.
+	pub overflow_capacity: usize,
+	/// How many more times to try sending a message to a full channel before keeping it
+	/// in the overflow.
+	///
+	/// The `Overseer` lets the other tasks run before each retry, which gives a `Subsystem`
+	/// that is just behind a chance to catch up without the message being kept.
+	pub delivery_retries: usize,
+	/// What to do with a message once both the channel and the overflow are full.
+	pub mailbox_policy: MailboxPolicy,
+	/// How many messages are taken from a `Subsystem` before dispatching the ones taken so far.
+	///
+	/// The `Subsystem`s are taken from in turns, so this bounds how long the messages
+	/// of one `Subsystem` may wait behind those of another one flooding the `Overseer`.
+	/// The overflow of a `Subsystem` is sent to it at most this many messages at a time as well.
+	pub messages_per_pass: usize,
+	/// How much the `Overseer` favours a `Subsystem` over the others, those not in here weigh 1.
+	///
+	/// A pass takes up to `messages_per_pass` times its weight messages from a `Subsystem`,
+	/// and the messages of the same [`Priority`] are dispatched to the heavier recipient
+	/// first, then from the heavier sender first. Unlike the priority of a message the
+	/// weight holds for everything a `Subsystem` sends and receives.
+	///
+	/// A weight does not starve the lighter `Subsystem`s. They are still taken from
+	/// in turns and a pass ends once any `Subsystem` has sent its share, while all
+	/// messages taken in a pass are dispatched before the next one. So the weights
+	/// only scale the shares of a pass and order the messages within it. A weight
+	/// of `0` is taken as `1`.
+	///
+	/// [`Priority`]: enum.Priority.html
+	pub weights: HashMap<SubsystemId, u32>,
+	/// How fast the `Subsystem`s may send messages, those not in here are not limited.
+	///
+	/// The messages are counted as the `Overseer` takes them from the `Subsystem`s,
+	/// so a `Subsystem` over its [`RateLimit`] is still taken from, but its messages
+	/// are not dispatched. An [`OverseerEvent::RateLimited`] is emitted for each
+	/// message that is dropped.
+	///
+	/// [`RateLimit`]: struct.RateLimit.html
+	/// [`OverseerEvent::RateLimited`]: enum.OverseerEvent.html#variant.RateLimited
+	pub rate_limits: HashMap<SubsystemId, RateLimit>,
+	/// How many messages may be in the mailboxes of all `Subsystem`s together.
+	///
+	/// Once that many messages have been dispatched but not received yet, the `Overseer`
+	/// stops taking messages from the `Subsystem`s and the outside world until the
+	/// `Subsystem`s have received some of them. So those sending are slowed down to the
+	/// pace of the slowest `Subsystem` rather than filling up the memory. The messages
+	/// taken in one pass are all dispatched, so the budget may be overshot by a pass.
+	/// Messages posted with [`SubsystemContext::post_msg`] keep piling up meanwhile.
+	///
+	/// If `None` there is no limit besides the capacities of the single mailboxes.
+	///
+	/// [`SubsystemContext::post_msg`]: struct.SubsystemContext.html#method.post_msg
+	pub message_budget: Option<usize>,
+	/// Pairs of `(subsystem, dependency)`, each dependency is started before
+	/// the `Subsystem`s that depend on it.
+	pub dependencies: Vec<(SubsystemId, SubsystemId)>,
+	/// Wait for the dependencies to signal that they are ready before starting
+	/// the `Subsystem`s that depend on them, but no longer than this.
+	///
+	/// If `None` the `Subsystem`s are started right away.
+	pub ready_timeout: Option<Duration>,
+	/// A `Subsystem` that has not sent a heartbeat for this long is unhealthy.
+	///
+	/// If `None` the health of the `Subsystem`s is not checked.
+	pub liveness_timeout: Option<Duration>,
+	/// Stop unhealthy `Subsystem`s with an error, so that the `restart_policy`
+	/// and the `exit_policy` apply to them.
+	pub restart_unhealthy: bool,
+	/// Stop the `Overseer` once all of its `Subsystem`s have finished, with none to be restarted.
+	///
+	/// Otherwise the `Overseer` keeps running without any `Subsystem`s, waiting for
+	/// new ones to be spawned with [`OverseerHandler::spawn_subsystem`].
+	///
+	/// [`OverseerHandler::spawn_subsystem`]: struct.OverseerHandler.html#method.spawn_subsystem
+	pub stop_when_empty: bool,
+	/// Stop the `Overseer` once the watchdog has noticed that it is no longer making progress.
+	///
+	/// Otherwise the watchdog only reports the stall, see [`Overseer::with_watchdog`].
+	///
+	/// [`Overseer::with_watchdog`]: struct.Overseer.html#method.with_watchdog
+	pub stop_on_stall: bool,
+	/// Where to send a [`Record`] of every dispatched message.
+	///
+	/// [`Record`]: struct.Record.html
+	#[cfg(feature = "record")]
+	pub recorder: Option<mpsc::UnboundedSender<Record>>,
+	/// Where to forward messages to `Subsystem`s that are not running, or with
+	/// [`MailboxPolicy::DeadLetter`] whose mailbox is full.
+	///
+	/// Messages are dropped if this channel is full.
+	///
+	/// [`MailboxPolicy::DeadLetter`]: enum.MailboxPolicy.html#variant.DeadLetter
+	pub dead_letters: Option<mpsc::Sender<(SubsystemId, AllMessages)>>,
+	/// Log the whole dispatched messages rather than only their [`MessageKind`].
+	///
+	/// The messages are logged at `trace` level either way.
+	///
+	/// [`MessageKind`]: trait.MessageKind.html
+	pub log_payloads: bool,
+	/// How many of the last dispatched messages to keep a [`DispatchEntry`] about,
+	/// see [`OverseerHandler::recent_dispatches`].
+	///
+	/// The entries are kept in memory without the messages themselves, so this is
+	/// lighter than recording. If `0` nothing is kept.
+	///
+	/// [`DispatchEntry`]: struct.DispatchEntry.html
+	/// [`OverseerHandler::recent_dispatches`]: struct.OverseerHandler.html#method.recent_dispatches
+	pub dispatch_log_capacity: usize,
+	/// Where the `Subsystem`s run their blocking work.
+	///
+	/// The executor the `Subsystem`s are spawned on is only required to implement `Spawn`,
+	/// so it may well be running them on a single thread. Hence blocking work is run
+	/// elsewhere, and if `None` on a [`LazyThreadPool`] of its own.
+	///
+	/// [`LazyThreadPool`]: struct.LazyThreadPool.html
+	pub blocking_pool: Option<Arc<dyn SpawnBlocking>>,
+	/// Where the `Overseer` and the `Subsystem`s take the time from.
+	///
+	/// If `None` this is the [`SystemClock`].
+	///
+	/// [`SystemClock`]: struct.SystemClock.html
+	pub clock: Option<Arc<dyn Clock>>,
+}
+
+impl OverseerConfig {
+	// The weight of a subsystem as configured, at least 1.
+	fn weight(&self, id: SubsystemId) -> u32 {
+		self.weights.get(&id).copied().unwrap_or(DEFAULT_WEIGHT).max(1)
 	}
+}
 
-	fn new(rx: mpsc::Receiver<FromOverseer<M>>, tx: mpsc::Sender<ToOverseer>) -> Self {
+impl Default for OverseerConfig {
+	fn default() -> Self {
 		Self {
-			rx,
-			tx,
+			exit_policy: ExitPolicy::default(),
+			restart_policy: None,
+			supervision: SupervisionStrategy::default(),
+			validation_channels: ChannelConfig::default(),
+			candidate_backing_channels: ChannelConfig::default(),
+			stop_grace_period: Duration::from_secs(STOP_DELAY),
+			drain_timeout: Duration::from_secs(DRAIN_TIMEOUT),
+			events_capacity: CHANNEL_CAPACITY,
+			overflow_capacity: CHANNEL_CAPACITY,
+			delivery_retries: 0,
+			mailbox_policy: MailboxPolicy::default(),
+			messages_per_pass: MESSAGES_PER_PASS,
+			weights: HashMap::new(),
+			rate_limits: HashMap::new(),
+			message_budget: None,
+			dependencies: Vec::new(),
+			ready_timeout: None,
+			liveness_timeout: None,
+			restart_unhealthy: false,
+			stop_when_empty: false,
+			stop_on_stall: false,
+			#[cfg(feature = "record")]
+			recorder: None,
+			dead_letters: None,
+			log_payloads: false,
+			dispatch_log_capacity: 0,
+			blocking_pool: None,
+			clock: None,
 		}
 	}
 }
 
-/// A trait that describes the [`Subsystem`]s that can run on the [`Overseer`].
+/// The health of a running [`Subsystem`].
 ///
-/// It is generic over the message type circulating in the system.
-/// The idea that we want some type contaning persistent state that
-/// can spawn actually running subsystems when asked to.
-///
-/// [`Overseer`]: struct.Overseer.html
 /// [`Subsystem`]: trait.Subsystem.html
-pub trait Subsystem<M: Debug> {
-	/// Start this `Subsystem` and return `SpawnedSubsystem`.
-	fn start(&mut self, ctx: SubsystemContext<M>) -> SpawnedSubsystem;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+	/// The `Subsystem` has recently sent a heartbeat.
+	Healthy,
+	/// The `Subsystem` has not sent a heartbeat within the `liveness_timeout`.
+	Unhealthy,
 }
 
-/// A subsystem that we oversee.
+/// How the [`Subsystem`]s have stopped along with the [`Overseer`].
 ///
-/// Ties together the [`Subsystem`] itself and it's running instance
-/// (which may be missing if the [`Subsystem`] is not running at the moment
-/// for whatever reason).
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StopReport {
+	/// The `Subsystem`s that have concluded in time.
+	pub clean: Vec<SubsystemId>,
+	/// The `Subsystem`s that have not concluded in time and have been dropped.
+	pub forced: Vec<SubsystemId>,
+}
+
+/// Why the [`Overseer`] has stopped.
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShutdownReason {
+	/// The `Subsystem`s have all finished, after the `Overseer` has been drained
+	/// or once there was nothing left to oversee.
+	Clean,
+	/// A `Subsystem` has failed in a way the `Overseer` could not recover from, with this error.
+	SubsystemFatal(SubsystemId, SubsystemError),
+	/// The `Overseer` has been stopped through an [`OverseerHandler`].
+	///
+	/// [`OverseerHandler`]: struct.OverseerHandler.html
+	ExternalStop,
+	/// The watchdog has noticed that the `Overseer` was no longer making progress,
+	/// see [`OverseerConfig::stop_on_stall`].
+	///
+	/// [`OverseerConfig::stop_on_stall`]: struct.OverseerConfig.html#structfield.stop_on_stall
+	WatchdogStall,
+}
+
+/// How the [`Overseer`] has stopped, see [`Overseer::run`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Overseer::run`]: struct.Overseer.html#method.run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+	/// Why the `Overseer` has stopped.
+	pub reason: ShutdownReason,
+	/// The `Subsystem`s that were still running as the `Overseer` started to stop.
+	pub running: Vec<SubsystemId>,
+}
+
+/// What the [`Overseer`] knows about one of the [`Subsystem`]s, for diagnostics.
 ///
+/// [`Overseer`]: struct.Overseer.html
 /// [`Subsystem`]: trait.Subsystem.html
-#[allow(dead_code)]
-struct OverseenSubsystem<M: Debug> {
-	subsystem: Box<dyn Subsystem<M> + Send>,
-	instance: Option<SubsystemInstance<M>>,
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsystemInfo {
+	/// The `Subsystem` this is about.
+	pub id: SubsystemId,
+	/// Whether the `Subsystem` is running.
+	pub running: bool,
+	/// How many messages have been dispatched to the `Subsystem` but not received by it yet.
+	pub mailbox_depth: u64,
+	/// How many times the `Subsystem` has been restarted.
+	pub restarts: usize,
+	/// How long ago a message has last been dispatched to the `Subsystem`, if any has been.
+	pub since_last_dispatch: Option<Duration>,
 }
 
-/// The `Overseer` itself.
-pub struct Overseer<S: Spawn> {
-	/// A validation subsystem
-	validation_subsystem: OverseenSubsystem<ValidationSubsystemMessage>,
+/// Which [`Subsystem`]s the [`Overseer`] oversees and how they depend on each other,
+/// to create another `Overseer` like it with [`Overseer::from_topology`].
+///
+/// The messages in flight are not part of it.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`Overseer::from_topology`]: struct.Overseer.html#method.from_topology
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub struct OverseerTopology {
+	/// The `Subsystem`s that are running or about to be, in the order of their dependencies.
+	pub subsystems: Vec<SubsystemId>,
+	/// The dependencies between the `Subsystem`s, see [`OverseerConfig::dependencies`].
+	///
+	/// [`OverseerConfig::dependencies`]: struct.OverseerConfig.html#structfield.dependencies
+	pub dependencies: Vec<(SubsystemId, SubsystemId)>,
+	/// How many times each of the `Subsystem`s has been restarted.
+	pub restarts: HashMap<SubsystemId, usize>,
+}
 
-	/// A candidate backing subsystem
-	candidate_backing_subsystem: OverseenSubsystem<CandidateBackingSubsystemMessage>,
+/// A message the [`Overseer`] has dispatched, kept for post-mortem debugging.
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchEntry {
+	/// The sender of the message, `None` if it came from the outside world.
+	pub from: Option<SubsystemId>,
+	/// The receiver of the message.
+	pub to: SubsystemId,
+	/// The [`MessageKind`] of the message.
+	///
+	/// [`MessageKind`]: trait.MessageKind.html
+	pub kind: &'static str,
+	/// When the message has been dispatched, by the clock of the `Overseer`.
+	pub at: Instant,
+}
 
-	/// Spawner to spawn tasks to.
-	s: S,
+/// How long the loop of the [`Overseer`] has spent on each of the [`Subsystem`]s so far.
+///
+/// The loop handles one message at a time, so a `Subsystem` with a lot of time
+/// spent on it is the one holding up the others.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[cfg(feature = "timing")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoopTimings {
+	/// Time spent handling what each `Subsystem` has sent, as it is taken from its channel.
+	pub collection: HashMap<SubsystemId, Duration>,
+	/// Time spent delivering messages to each `Subsystem`, including waiting for room in its mailbox.
+	pub dispatch: HashMap<SubsystemId, Duration>,
+}
 
-	/// Here we keep handles to spawned subsystems to be notified when they terminate.
-	running_subsystems: FuturesUnordered<RemoteHandle<()>>,
+// How the logs of an overseer start, with its name if it has one.
+#[derive(Clone, Copy)]
+struct LogPrefix(Option<&'static str>);
 
-	/// Gather running subsystms' outbound streams into one.
-	running_subsystems_rx: StreamUnordered<mpsc::Receiver<ToOverseer>>,
+impl std::fmt::Display for LogPrefix {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.0 {
+			Some(name) => write!(f, "{}: ", name),
+			None => Ok(()),
+		}
+	}
+}
 
-	/// Events that are sent to the overseer from the outside world
-	events_rx: mpsc::Receiver<Event>,
+// How the logs refer to a subsystem, by its name along with its id if it has a name.
+#[derive(Clone, Copy)]
+struct Named {
+	overseer: Option<&'static str>,
+	name: Option<&'static str>,
+	id: SubsystemId,
 }
 
-impl<S> Overseer<S>
-where
-	S: Spawn,
-{
-	/// Create a new intance of the `Overseer` with a fixed set of [`Subsystem`]s.
+impl Named {
+	fn prefix(&self) -> LogPrefix {
+		LogPrefix(self.overseer)
+	}
+}
+
+impl Debug for Named {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self.name {
+			Some(name) => write!(f, "{} ({:?})", name, self.id),
+			None => write!(f, "{:?}", self.id),
+		}
+	}
+}
+
+/// Counters of the messages passing through the [`Overseer`].
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Default)]
+struct Metrics {
+	validation: SubsystemMetrics,
+	candidate_backing: SubsystemMetrics,
+	undeliverable: AtomicU64,
+	// Wakes up the overseer waiting for the mailboxes to drain once a message is received.
+	received: AtomicWaker,
+}
+
+#[derive(Debug, Default)]
+struct SubsystemMetrics {
+	sent: AtomicU64,
+	dispatched: AtomicU64,
+	received: AtomicU64,
+	lagging: AtomicU64,
+}
+
+impl Metrics {
+	fn subsystem(&self, id: SubsystemId) -> &SubsystemMetrics {
+		match id {
+			SubsystemId::Validation => &self.validation,
+			SubsystemId::CandidateBacking => &self.candidate_backing,
+		}
+	}
+
+	fn snapshot(&self) -> MetricsSnapshot {
+		MetricsSnapshot {
+			subsystems: SubsystemId::ALL.iter()
+				.map(|id| (*id, self.subsystem(*id).snapshot()))
+				.collect(),
+			undeliverable: self.undeliverable.load(Ordering::Relaxed),
+		}
+	}
+
+	// How many messages have been dispatched to the subsystems but not received yet.
+	fn in_flight(&self) -> u64 {
+		SubsystemId::ALL.iter()
+			.map(|id| self.subsystem(*id).snapshot().mailbox_depth)
+			.sum()
+	}
+}
+
+impl SubsystemMetrics {
+	fn snapshot(&self) -> SubsystemMetricsSnapshot {
+		let received = self.received.load(Ordering::Relaxed);
+
+		SubsystemMetricsSnapshot {
+			sent: self.sent.load(Ordering::Relaxed),
+			received,
+			mailbox_depth: self.dispatched.load(Ordering::Relaxed).saturating_sub(received),
+			lagging: self.lagging.load(Ordering::Relaxed),
+		}
+	}
+
+	// Forget about the messages left in the channels of a stopped instance.
+	fn reset_mailbox(&self) {
+		self.dispatched.store(self.received.load(Ordering::Relaxed), Ordering::Relaxed);
+	}
+}
+
+/// The values of the [`Overseer`] metrics at some point in time.
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+	/// Metrics of each of the `Subsystem`s.
+	pub subsystems: HashMap<SubsystemId, SubsystemMetricsSnapshot>,
+	/// Messages that were dropped because their `Subsystem` was not running or had concluded,
+	/// or its mailbox was full with the `MailboxPolicy::DeadLetter`.
+	pub undeliverable: u64,
+}
+
+/// The values of the metrics of a single [`Subsystem`].
+///
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubsystemMetricsSnapshot {
+	/// Messages sent by the `Subsystem`.
+	pub sent: u64,
+	/// Messages received by the `Subsystem`.
+	pub received: u64,
+	/// Messages dispatched to the `Subsystem` that it has not received yet.
+	pub mailbox_depth: u64,
+	/// How many times the mailbox of the `Subsystem` has grown to its `high_water_mark`.
+	pub lagging: u64,
+}
+
+/// An identifier assigned by the [`Overseer`] to every message it dispatches.
+///
+/// The identifiers are increasing in the order the messages are dispatched.
+///
+/// [`Overseer`]: struct.Overseer.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub struct MessageId(pub u64);
+
+/// A message dispatched by the [`Overseer`].
+///
+/// These are sent to the [`OverseerConfig::recorder`] and can be passed to
+/// [`Overseer::replay`] to deliver the same messages again.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`OverseerConfig::recorder`]: struct.OverseerConfig.html#structfield.recorder
+/// [`Overseer::replay`]: struct.Overseer.html#method.replay
+#[cfg(feature = "record")]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Record {
+	/// The identifier of the message, which is also the logical time of its dispatch.
+	pub id: MessageId,
+	/// The sender of the message, `None` if it came from the outside world.
+	pub from: Option<SubsystemId>,
+	/// The receiver of the message.
+	pub to: SubsystemId,
+	/// The message itself.
+	pub msg: AllMessages,
+}
+
+/// An identifier of each of the [`Subsystem`]s run by the [`Overseer`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub enum SubsystemId {
+	/// The validation `Subsystem`.
+	Validation,
+	/// The candidate backing `Subsystem`.
+	CandidateBacking,
+}
+
+impl SubsystemId {
+	const ALL: [SubsystemId; 2] = [SubsystemId::Validation, SubsystemId::CandidateBacking];
+}
+
+/// A type of messages that are sent from [`Subsystem`] to [`Overseer`].
+///
+/// It wraps a system-wide [`AllMessages`] type that represents all possible
+/// messages in the system.
+///
+/// [`AllMessages`]: enum.AllMessages.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`Overseer`]: struct.Overseer.html
+enum ToOverseer {
+	/// This is a message sent by a `Subsystem`.
+	SubsystemMessage {
+		msg: AllMessages,
+		/// The message the `Subsystem` was handling when it sent this one.
+		causation_id: Option<MessageId>,
+		priority: Priority,
+		/// When the message is no longer worth delivering, if ever.
+		deadline: Option<Instant>,
+		/// The span the message has been sent in.
+		span: MessageSpan,
+	},
+
+	/// A message that wraps something the `Subsystem` is desiring to
+	/// spawn on the overseer and a `oneshot::Sender` to signal the result
+	/// of the spawn.
+	SpawnJob {
+		s: BoxFuture<'static, ()>,
+		res: oneshot::Sender<SubsystemResult<()>>,
+	},
+
+	/// The `Subsystem` is ready to be depended on.
+	Ready,
+
+	/// The `Subsystem` is alive and processing its messages.
+	Heartbeat,
+
+	/// The `Subsystem` asks which other `Subsystem`s are running.
+	Peers(oneshot::Sender<Vec<SubsystemId>>),
+
+	/// The `Subsystem` has concluded its work for good and receives nothing anymore.
+	Concluded,
+}
+
+/// Some event from outer world.
+enum Event {
+	BlockImport,
+	BlockFinalized,
+	MsgToSubsystem(AllMessages),
+	ListSubsystems(oneshot::Sender<Vec<SubsystemId>>),
+	Subscribe(mpsc::Sender<OverseerEvent>),
+	AwaitReady(SubsystemId, oneshot::Sender<()>),
+	AwaitSubsystem(SubsystemId, oneshot::Sender<SubsystemResult<()>>),
+	Health(oneshot::Sender<HashMap<SubsystemId, Health>>),
+	Inspect(oneshot::Sender<Vec<SubsystemInfo>>),
+	DebugDump(oneshot::Sender<String>),
+	Topology(oneshot::Sender<OverseerTopology>),
+	RecentDispatches(oneshot::Sender<Vec<DispatchEntry>>),
+	Pause(SubsystemId),
+	Resume(SubsystemId),
+	Drain,
+	Stop,
+	StopWithTimeout(Duration, oneshot::Sender<StopReport>),
+	ConcludeAll(Duration, oneshot::Sender<StopReport>),
+	SpawnSubsystem(AnySubsystem, oneshot::Sender<SubsystemResult<SubsystemId>>),
+	ReplaceSubsystem(AnySubsystem, oneshot::Sender<SubsystemResult<SubsystemId>>),
+	#[cfg(feature = "timing")]
+	Timings(oneshot::Sender<LoopTimings>),
+}
+
+/// An event describing what is happening inside of the [`Overseer`].
+///
+/// These can be observed with [`OverseerHandler::events`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`OverseerHandler::events`]: struct.OverseerHandler.html#method.events
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverseerEvent {
+	/// A `Subsystem` has been (re)started.
+	SubsystemStarted(SubsystemId),
+	/// A `Subsystem` has concluded or has been stopped by the `Overseer`.
+	SubsystemStopped(SubsystemId),
+	/// A `Subsystem` has failed.
+	SubsystemFailed(SubsystemId),
+	/// A `Subsystem` has finished with `Ok(())` without having concluded with
+	/// [`SubsystemContext::conclude`].
 	///
-	/// Each [`Subsystem`] is passed to this function as an explicit parameter
-	/// and is supposed to implement some interface that is generic over message type
-	/// that is specific to this [`Subsystem`]. At the moment there are only two
-	/// subsystems:
-	///   * Validation
-	///   * CandidateBacking
+	/// [`SubsystemContext::conclude`]: struct.SubsystemContext.html#method.conclude
+	SubsystemExited(SubsystemId),
+	/// A `Subsystem` has not sent a heartbeat within the `liveness_timeout`.
+	SubsystemUnhealthy(SubsystemId),
+	/// The mailbox of a `Subsystem` has grown to its `high_water_mark`, with this many messages.
+	SubsystemLagging(SubsystemId, u64),
+	/// A message has been delivered to a `Subsystem`.
+	MessageDispatched {
+		/// The identifier assigned to the message.
+		id: MessageId,
+		/// The message its sender was handling when it sent this one.
+		causation_id: Option<MessageId>,
+		/// The sender of the message, `None` if it came from the outside world.
+		from: Option<SubsystemId>,
+		/// The receiver of the message.
+		to: SubsystemId,
+	},
+	/// A message has been dropped because its receiver is not running or has concluded,
+	/// or its mailbox is full with the `MailboxPolicy::DeadLetter`.
+	MessageUndeliverable {
+		/// The sender of the message, `None` if it came from the outside world.
+		from: Option<SubsystemId>,
+		/// The intended receiver of the message.
+		to: SubsystemId,
+	},
+	/// The messages to a `Subsystem` have filled up both its channel and the overflow,
+	/// so the `mailbox_policy` applies to the next one.
+	MailboxOverflow(SubsystemId),
+	/// A message has been dropped because its deadline has passed before it could be dispatched.
+	MessageExpired {
+		/// The sender of the message, `None` if it came from the outside world.
+		from: Option<SubsystemId>,
+		/// The intended receiver of the message.
+		to: SubsystemId,
+	},
+	/// A message from a `Subsystem` over its [`RateLimit`] has been dropped.
 	///
-	/// As any entity that satisfies the interface may act as a [`Subsystem`] this allows
-	/// mocking in the test code:
+	/// [`RateLimit`]: struct.RateLimit.html
+	RateLimited(SubsystemId),
+}
+
+/// Some message that is sent from one of the `Subsystem`s to the outside world.
+pub enum OutboundMessage {
+	SubsystemMessage {
+		msg: AllMessages,
+	}
+}
+
+/// A handler used to communicate with the [`Overseer`].
+///
+/// [`Overseer`]: struct.Overseer.html
+pub struct OverseerHandler {
+	events_tx: mpsc::Sender<Event>,
+	metrics: Arc<Metrics>,
+	// Whether the `Overseer` has been asked to drain, after which no messages are sent.
+	draining: bool,
+}
+
+impl OverseerHandler {
+	/// Get the current values of the [`Overseer`] metrics.
 	///
-	/// ```text
-	///                  +------------------------------------+
-	///                  |            Overseer                |
-	///                  +------------------------------------+
-	///                    /            |             |      \
-	///      ................. subsystems...................................
-	///      . +-----------+    +-----------+   +----------+   +---------+ .
-	///      . |           |    |           |   |          |   |         | .
-	///      . +-----------+    +-----------+   +----------+   +---------+ .
-	///      ...............................................................
-	///                              |
-	///                        probably `spawn`
-	///                            a `job`
-	///                              |
-	///                              V
-	///                         +-----------+
-	///                         |           |
-	///                         +-----------+
+	/// [`Overseer`]: struct.Overseer.html
+	pub fn metrics(&self) -> MetricsSnapshot {
+		self.metrics.snapshot()
+	}
+
+	/// Inform the `Overseer` that that some block was imported.
+	pub async fn block_imported(&mut self) -> SubsystemResult<()> {
+		self.events_tx.send(Event::BlockImport).await?;
+
+		Ok(())
+	}
+
+	/// Send some message to one of the `Subsystem`s.
 	///
-	/// ```
+	/// The channel to the `Overseer` holds [`OverseerConfig::events_capacity`] messages
+	/// and requests, once it is full this waits for the `Overseer` to take from it.
 	///
-	/// [`Subsystem`]: trait.Subsystem.html
+	/// Once the `Overseer` has been asked to [`drain`] this fails with `SubsystemError::Draining`.
 	///
-	/// # Example
+	/// [`OverseerConfig::events_capacity`]: struct.OverseerConfig.html#structfield.events_capacity
+	/// [`drain`]: #method.drain
+	pub async fn send_msg(&mut self, msg: AllMessages) -> SubsystemResult<()> {
+		if self.draining {
+			return Err(SubsystemError::Draining);
+		}
+
+		self.events_tx.send(Event::MsgToSubsystem(msg)).await?;
+
+		Ok(())
+	}
+
+	/// Send some message to one of the `Subsystem`s without waiting for room in the channel.
 	///
-	/// The [`Subsystems`] may be any type as long as they implement an expected interface.
-	/// Here, we create two mock subsystems and start the `Overseer` with them. For the sake
-	/// of simplicity the termination of the example is done with a timeout.
-	/// ```
-	/// # use std::time::Duration;
-	/// # use futures::{executor, pin_mut, select, FutureExt};
-	/// # use futures_timer::Delay;
-	/// # use overseer::{
-	/// #     Overseer, Subsystem, SpawnedSubsystem, SubsystemContext,
-	/// #     ValidationSubsystemMessage, CandidateBackingSubsystemMessage,
-	/// # };
+	/// If the channel to the `Overseer` is full or closed the message is handed back,
+	/// so that it can be dropped, buffered or sent again later. So is it once the `Overseer`
+	/// has been asked to drain, with `SubsystemError::Draining`.
+	pub fn try_send_msg(&mut self, msg: AllMessages) -> Result<(), TrySendError> {
+		if self.draining {
+			return Err(TrySendError { error: SubsystemError::Draining, msg });
+		}
+
+		self.events_tx.try_send(Event::MsgToSubsystem(msg)).map_err(|e| {
+			let error = if e.is_full() {
+				SubsystemError::MailboxFull
+			} else {
+				SubsystemError::ChannelClosed
+			};
+
+			match e.into_inner() {
+				Event::MsgToSubsystem(msg) => TrySendError { error, msg },
+				_ => unreachable!("a `MsgToSubsystem` has been sent"),
+			}
+		})
+	}
+
+	/// Inform the `Overseer` that that some block was finalized.
+	pub async fn block_finalized(&mut self) -> SubsystemResult<()> {
+		self.events_tx.send(Event::BlockFinalized).await?;
+
+		Ok(())
+	}
+
+	/// Get the `Subsystem`s that are currently running.
+	pub async fn list_subsystems(&mut self) -> SubsystemResult<Vec<SubsystemId>> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::ListSubsystems(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Subscribe to the [`OverseerEvent`]s.
 	///
-	/// struct ValidationSubsystem;
-	/// impl Subsystem<ValidationSubsystemMessage> for ValidationSubsystem {
-	///     fn start(
-	///         &mut self,
-	///         mut ctx: SubsystemContext<ValidationSubsystemMessage>,
-	///     ) -> SpawnedSubsystem {
-	///         SpawnedSubsystem(Box::pin(async move {
-	///             loop {
-	///                 Delay::new(Duration::from_secs(1)).await;
-	///             }
-	///         }))
-	///     }
-	/// }
+	/// Every subscriber receives each event emitted after it has subscribed.
+	/// Events are dropped for subscribers that don't keep up with them.
 	///
-	/// struct CandidateBackingSubsystem;
-	/// impl Subsystem<CandidateBackingSubsystemMessage> for CandidateBackingSubsystem {
-	///     fn start(
-	///         &mut self,
-	///         mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>,
-	///     ) -> SpawnedSubsystem {
-	///         SpawnedSubsystem(Box::pin(async move {
-	///             loop {
-	///                 Delay::new(Duration::from_secs(1)).await;
-	///             }
-	///         }))
-	///     }
-	/// }
+	/// [`OverseerEvent`]: enum.OverseerEvent.html
+	pub async fn events(&mut self) -> SubsystemResult<mpsc::Receiver<OverseerEvent>> {
+		let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+		self.events_tx.send(Event::Subscribe(tx)).await?;
+
+		Ok(rx)
+	}
+
+	/// Wait until the given `Subsystem` has signaled that it is ready.
+	pub async fn await_ready(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::AwaitReady(id, tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Spawn a `Subsystem` in the place of one that is not running, such as one that has concluded.
 	///
-	/// # fn main() { executor::block_on(async move {
-	/// let spawner = executor::ThreadPool::new().unwrap();
-	/// let (overseer, _handler) = Overseer::new(
-	///     Box::new(ValidationSubsystem),
-	///     Box::new(CandidateBackingSubsystem),
-	///     spawner,
-	/// ).unwrap();
+	/// This lets optional `Subsystem`s be loaded once the `Overseer` is running. The
+	/// `Subsystem` waits for its dependencies to be ready just as it would have when
+	/// the `Overseer` was created, and it is restarted by the same policies. Fails with
+	/// `SubsystemError::AlreadyRunning` if another `Subsystem` is running in its place.
+	pub async fn spawn_subsystem(&mut self, s: AnySubsystem) -> SubsystemResult<SubsystemId> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::SpawnSubsystem(s, tx)).await?;
+
+		rx.await?
+	}
+
+	/// Replace a running `Subsystem` with another implementation under the same id.
 	///
-	/// let timer = Delay::new(Duration::from_millis(50)).fuse();
+	/// The running `Subsystem` is sent the `Conclude` signal after the messages already
+	/// in its channel, and the new one is started with a fresh context once the old one
+	/// has finished. The messages sent to the id meanwhile are kept, up to the
+	/// [`OverseerConfig::overflow_capacity`], and delivered to the new `Subsystem`. If
+	/// nothing is running in its place the `Subsystem` is spawned as with [`spawn_subsystem`].
+	/// Fails with `SubsystemError::AlreadyRunning` if it is being replaced already.
 	///
-	/// let overseer_fut = overseer.run().fuse();
-	/// pin_mut!(timer);
-	/// pin_mut!(overseer_fut);
+	/// [`OverseerConfig::overflow_capacity`]: struct.OverseerConfig.html#structfield.overflow_capacity
+	/// [`spawn_subsystem`]: #method.spawn_subsystem
+	pub async fn replace_subsystem(&mut self, s: AnySubsystem) -> SubsystemResult<SubsystemId> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::ReplaceSubsystem(s, tx)).await?;
+
+		rx.await?
+	}
+
+	/// Wait for a `Subsystem` to finish and get the result it has finished with.
 	///
-	/// select! {
-	///     _ = overseer_fut => (),
-	///     _ = timer => (),
-	/// }
-	/// #
-	/// # }); }
-	/// ```
-	pub fn new(
-		validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send>,
-		candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>,
-		mut s: S,
-	) -> SubsystemResult<(Self, OverseerHandler)> {
-		let (events_tx, events_rx) = mpsc::channel(CHANNEL_CAPACITY);
+	/// If the `Subsystem` is not running this returns the result of its last run,
+	/// or `SubsystemError::UnknownSubsystem` if it has never been run.
+	pub async fn await_subsystem(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::AwaitSubsystem(id, tx)).await?;
+
+		rx.await?
+	}
+
+	/// Get the [`Health`] of the `Subsystem`s that are currently running.
+	///
+	/// [`Health`]: enum.Health.html
+	pub async fn health(&mut self) -> SubsystemResult<HashMap<SubsystemId, Health>> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::Health(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Get how long the [`Overseer`] has spent on each of the `Subsystem`s so far.
+	///
+	/// [`Overseer`]: struct.Overseer.html
+	#[cfg(feature = "timing")]
+	pub async fn timings(&mut self) -> SubsystemResult<LoopTimings> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::Timings(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Get a [`SubsystemInfo`] about each of the `Subsystem`s, as they are right now.
+	///
+	/// [`SubsystemInfo`]: struct.SubsystemInfo.html
+	pub async fn inspect(&mut self) -> SubsystemResult<Vec<SubsystemInfo>> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::Inspect(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Get a human readable description of the state of the [`Overseer`], for the logs.
+	///
+	/// It is taken between two messages being handled, so it is consistent.
+	///
+	/// [`Overseer`]: struct.Overseer.html
+	pub async fn debug_dump(&mut self) -> SubsystemResult<String> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::DebugDump(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Get the [`OverseerTopology`] of the [`Overseer`], to create another one like it.
+	///
+	/// [`OverseerTopology`]: struct.OverseerTopology.html
+	/// [`Overseer`]: struct.Overseer.html
+	pub async fn topology(&mut self) -> SubsystemResult<OverseerTopology> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::Topology(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Get a [`DispatchEntry`] about each of the last dispatched messages, the oldest first.
+	///
+	/// Up to [`OverseerConfig::dispatch_log_capacity`] of them are kept.
+	///
+	/// [`DispatchEntry`]: struct.DispatchEntry.html
+	/// [`OverseerConfig::dispatch_log_capacity`]: struct.OverseerConfig.html#structfield.dispatch_log_capacity
+	pub async fn recent_dispatches(&mut self) -> SubsystemResult<Vec<DispatchEntry>> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::RecentDispatches(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Stop delivering messages to a `Subsystem` until it is resumed, without stopping it.
+	///
+	/// The messages to the `Subsystem` are kept meanwhile, up to the
+	/// [`OverseerConfig::overflow_capacity`], the ones past that are dropped.
+	///
+	/// [`OverseerConfig::overflow_capacity`]: struct.OverseerConfig.html#structfield.overflow_capacity
+	pub async fn pause_subsystem(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		self.events_tx.send(Event::Pause(id)).await?;
+
+		Ok(())
+	}
+
+	/// Deliver the messages kept for a paused `Subsystem`, and any further ones, again.
+	pub async fn resume_subsystem(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		self.events_tx.send(Event::Resume(id)).await?;
+
+		Ok(())
+	}
+
+	/// Tell `Overseer` to shutdown once the `Subsystem`s finish their pending work.
+	///
+	/// The messages sent before this are all delivered, while sending any further
+	/// ones fails with `SubsystemError::Draining`. Every `Subsystem` gets to process
+	/// the messages already queued for it before it receives a `Conclude` signal. The `Overseer` exits when all of the
+	/// `Subsystem`s have concluded, or stops them once the [`OverseerConfig::drain_timeout`]
+	/// has passed. Use [`stop`] to shutdown immediately.
+	///
+	/// [`stop`]: #method.stop
+	/// [`OverseerConfig::drain_timeout`]: struct.OverseerConfig.html#structfield.drain_timeout
+	pub async fn drain(&mut self) -> SubsystemResult<()> {
+		self.events_tx.send(Event::Drain).await?;
+		self.draining = true;
+
+		Ok(())
+	}
+
+	/// Tell `Overseer` to shutdown.
+	///
+	/// Once the `Overseer` has stopped any further calls will return an error.
+	pub async fn stop(&mut self) -> SubsystemResult<()> {
+		self.events_tx.send(Event::Stop).await?;
+
+		Ok(())
+	}
+
+	/// Tell `Overseer` to shutdown, giving the `Subsystem`s `timeout` to conclude.
+	///
+	/// The `Subsystem`s that have not concluded by then are dropped,
+	/// the returned [`StopReport`] tells which ones they are.
+	///
+	/// [`StopReport`]: struct.StopReport.html
+	pub async fn stop_with_timeout(&mut self, timeout: Duration) -> SubsystemResult<StopReport> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::StopWithTimeout(timeout, tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Tell `Overseer` to conclude every `Subsystem` in turn and then shutdown.
+	///
+	/// Unlike with [`stop_with_timeout`] a `Subsystem` keeps its channels until it has
+	/// concluded, and the `Subsystem`s conclude in the reverse order of their dependencies.
+	/// A `Subsystem` is sent the `Conclude` signal once all of those depending on it have
+	/// finished, so they can still send it messages while concluding. The `Subsystem`s
+	/// that have not concluded within `timeout` are dropped, the returned [`StopReport`]
+	/// tells which ones they are.
+	///
+	/// [`stop_with_timeout`]: #method.stop_with_timeout
+	/// [`StopReport`]: struct.StopReport.html
+	pub async fn conclude_all(&mut self, timeout: Duration) -> SubsystemResult<StopReport> {
+		let (tx, rx) = oneshot::channel();
+		self.events_tx.send(Event::ConcludeAll(timeout, tx)).await?;
+
+		Ok(rx.await?)
+	}
+}
+
+impl Debug for ToOverseer {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ToOverseer::SubsystemMessage { msg, .. } => {
+				write!(f, "OverseerMessage::SubsystemMessage({:?})", msg)
+			}
+			ToOverseer::SpawnJob { .. } => write!(f, "OverseerMessage::Spawn(..)"),
+			ToOverseer::Ready => write!(f, "OverseerMessage::Ready"),
+			ToOverseer::Heartbeat => write!(f, "OverseerMessage::Heartbeat"),
+			ToOverseer::Peers(_) => write!(f, "OverseerMessage::Peers(..)"),
+			ToOverseer::Concluded => write!(f, "OverseerMessage::Concluded"),
+		}
+	}
+}
+
+/// A running instance of some [`Subsystem`].
+///
+/// [`Subsystem`]: trait.Subsystem.html
+struct SubsystemInstance<M: Debug> {
+	tx: SubsystemSender<Envelope<M>>,
+	signals_tx: mpsc::UnboundedSender<OverseerSignal>,
+	abort: AbortHandle,
+	// Requests the cancellation of the work of the subsystem, as does dropping it.
+	cancel: oneshot::Sender<()>,
+	// Messages waiting for room in the channel to the subsystem.
+	overflow: VecDeque<Envelope<M>>,
+	// Whether the subsystem has concluded its work with `SubsystemContext::conclude`.
+	concluded: Arc<AtomicBool>,
+}
+
+// What has become of a message handed to `SubsystemInstance::deliver`.
+enum Delivery<M> {
+	// The message has been sent or kept in the overflow.
+	Sent,
+	// The overflow is full, the message has been kept past its capacity until there is room.
+	Overflowing,
+	// There is no room for the message, which is handed back.
+	Full(M),
+}
+
+impl<M: Debug> Delivery<M> {
+	// There is no room for the message in the envelope.
+	fn full(envelope: Envelope<M>) -> Self {
+		match envelope.msg {
+			FromOverseer::Communication { msg } => Delivery::Full(msg),
+			FromOverseer::Signal(_) => unreachable!("signals are sent on a channel of their own"),
+		}
+	}
+
+	fn map<T>(self, f: impl FnOnce(M) -> T) -> Delivery<T> {
+		match self {
+			Delivery::Sent => Delivery::Sent,
+			Delivery::Overflowing => Delivery::Overflowing,
+			Delivery::Full(msg) => Delivery::Full(f(msg)),
+		}
+	}
+}
+
+impl<M: Debug> SubsystemInstance<M> {
+	// Send a message without waiting for room in the channel, trying again `retries` times
+	// and keeping it in the overflow if there is still none. Once the overflow is full
+	// the `policy` applies.
+	//
+	// Messages to a paused subsystem are all kept in the overflow, as long as there is room.
+	// Once there is none they are handed back, as waiting for a paused subsystem would never end.
+	async fn deliver(
+		&mut self,
+		envelope: Envelope<M>,
+		overflow_capacity: usize,
+		retries: usize,
+		policy: MailboxPolicy,
+		paused: bool,
+	) -> SubsystemResult<Delivery<M>> {
+		if paused {
+			if self.overflow.len() >= overflow_capacity {
+				return Ok(Delivery::full(envelope));
+			}
+
+			self.overflow.push_back(envelope);
+			return Ok(Delivery::Sent);
+		}
+
+		let envelope = if self.overflow.is_empty() {
+			let mut envelope = envelope;
+			for attempt in 0..=retries {
+				if attempt > 0 {
+					yield_now().await;
+				}
+
+				envelope = match self.tx.try_send(envelope)? {
+					Some(envelope) => envelope,
+					None => return Ok(Delivery::Sent),
+				};
+			}
+
+			envelope
+		} else {
+			envelope
+		};
+
+		if self.overflow.len() < overflow_capacity {
+			self.overflow.push_back(envelope);
+			return Ok(Delivery::Sent);
+		}
+
+		match policy {
+			MailboxPolicy::Wait => {
+				self.overflow.push_back(envelope);
+				Ok(Delivery::Overflowing)
+			}
+			MailboxPolicy::DeadLetter => Ok(Delivery::full(envelope)),
+		}
+	}
+
+	// Wait for the channel to take the messages kept past the capacity of the overflow.
+	async fn make_room(&mut self, overflow_capacity: usize) -> SubsystemResult<()> {
+		while self.overflow.len() > overflow_capacity {
+			if let Some(envelope) = self.overflow.pop_front() {
+				self.tx.send(envelope).await?;
+			}
+		}
+
+		Ok(())
+	}
+
+	// Queue a signal behind the messages to the subsystem without waiting for room in the channel.
+	//
+	// A signal is never dropped, so once the channel is full it is kept in the overflow
+	// even past its capacity.
+	fn queue_signal(&mut self, signal: OverseerSignal) {
+		let envelope = Envelope { id: None, msg: FromOverseer::Signal(signal), span: MessageSpan::none() };
+
+		let envelope = if self.overflow.is_empty() {
+			match self.tx.try_send(envelope) {
+				Ok(Some(envelope)) => envelope,
+				// Sent, or the subsystem is gone along with the need to signal it.
+				Ok(None) | Err(_) => return,
+			}
+		} else {
+			envelope
+		};
+
+		self.overflow.push_back(envelope);
+	}
+
+	// Whether a signal to conclude is waiting in the overflow.
+	fn is_concluding(&self) -> bool {
+		self.overflow.iter().any(|envelope| matches!(envelope.msg, FromOverseer::Signal(OverseerSignal::Conclude)))
+	}
+
+	// Send the overflow as the channel makes room for it, ready once anything has been sent.
+	// At most `limit` messages are sent at a time, so that a subsystem receiving as fast
+	// as its overflow is sent does not keep the overseer from everything else.
+	fn poll_overflow(&mut self, cx: &mut Context, limit: usize) -> Poll<()> {
+		let mut sent = false;
+
+		for _ in 0..limit {
+			if self.overflow.is_empty() {
+				break;
+			}
+
+			match self.tx.poll_ready(cx) {
+				Poll::Ready(Ok(())) => {
+					sent = true;
+					if let Some(envelope) = self.overflow.pop_front() {
+						// What follows a conclusion may be for a replacement, so it ends the pass.
+						let concluded = matches!(envelope.msg, FromOverseer::Signal(OverseerSignal::Conclude));
+						if self.tx.start_send(envelope).is_err() {
+							self.overflow.clear();
+						}
+						if concluded {
+							break;
+						}
+					}
+				}
+				// The subsystem is gone, along with the need to send it anything.
+				Poll::Ready(Err(_)) => {
+					self.overflow.clear();
+					sent = true;
+				}
+				Poll::Pending => break,
+			}
+		}
+
+		if sent {
+			Poll::Ready(())
+		} else {
+			Poll::Pending
+		}
+	}
+}
+
+// A message on its way to a subsystem.
+struct Envelope<M: Debug> {
+	// The identifier of a communication, signals have none.
+	id: Option<MessageId>,
+	msg: FromOverseer<M>,
+	// The span the message has been sent in.
+	span: MessageSpan,
+}
+
+// The tracing span a message has been sent in, the span it is received in follows from it.
+//
+// Without the `tracing` feature there is no span to carry.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Debug)]
+struct MessageSpan(tracing::Span);
+
+#[cfg(not(feature = "tracing"))]
+#[derive(Clone, Debug)]
+struct MessageSpan;
+
+#[cfg(feature = "tracing")]
+impl MessageSpan {
+	// No span, for signals and the messages from the outside world.
+	fn none() -> Self {
+		MessageSpan(tracing::Span::none())
+	}
+
+	// The span to send a message in while handling the one received in this span,
+	// unless the sender has entered a span of its own.
+	fn current(&self) -> Self {
+		let current = tracing::Span::current();
+		if current.is_none() {
+			self.clone()
+		} else {
+			MessageSpan(current)
+		}
+	}
+
+	// The span a message sent in this span is received in.
+	fn received(&self, subsystem: SubsystemId, id: Option<MessageId>) -> Self {
+		let span = tracing::debug_span!("message", subsystem = ?subsystem, id = ?id);
+		span.follows_from(&self.0);
+		MessageSpan(span)
+	}
+}
+
+#[cfg(not(feature = "tracing"))]
+impl MessageSpan {
+	fn none() -> Self {
+		MessageSpan
+	}
+
+	fn current(&self) -> Self {
+		MessageSpan
+	}
+
+	fn received(&self, _: SubsystemId, _: Option<MessageId>) -> Self {
+		MessageSpan
+	}
+}
+
+/// A sending side of a channel to some [`Subsystem`].
+///
+/// [`Subsystem`]: trait.Subsystem.html
+enum SubsystemSender<T> {
+	Bounded(mpsc::Sender<T>),
+	Unbounded(mpsc::UnboundedSender<T>),
+}
+
+impl<T> Clone for SubsystemSender<T> {
+	fn clone(&self) -> Self {
+		match self {
+			SubsystemSender::Bounded(tx) => SubsystemSender::Bounded(tx.clone()),
+			SubsystemSender::Unbounded(tx) => SubsystemSender::Unbounded(tx.clone()),
+		}
+	}
+}
+
+impl<T> SubsystemSender<T> {
+	// Send a message, this only waits for a bounded channel to have free capacity.
+	async fn send(&mut self, msg: T) -> SubsystemResult<()> {
+		match self {
+			SubsystemSender::Bounded(tx) => tx.send(msg).await?,
+			SubsystemSender::Unbounded(tx) => tx.unbounded_send(msg)?,
+		}
+
+		Ok(())
+	}
+
+	// Send a message if there is room for it, otherwise hand it back.
+	fn try_send(&mut self, msg: T) -> SubsystemResult<Option<T>> {
+		match self {
+			SubsystemSender::Bounded(tx) => match tx.try_send(msg) {
+				Ok(()) => Ok(None),
+				Err(e) if e.is_full() => Ok(Some(e.into_inner())),
+				Err(e) => Err(e.into()),
+			},
+			SubsystemSender::Unbounded(tx) => {
+				tx.unbounded_send(msg)?;
+				Ok(None)
+			}
+		}
+	}
+
+	fn poll_ready(&mut self, cx: &mut Context) -> Poll<SubsystemResult<()>> {
+		match self {
+			SubsystemSender::Bounded(tx) => tx.poll_ready(cx).map_err(Into::into),
+			SubsystemSender::Unbounded(_) => Poll::Ready(Ok(())),
+		}
+	}
+
+	fn start_send(&mut self, msg: T) -> SubsystemResult<()> {
+		match self {
+			SubsystemSender::Bounded(tx) => tx.start_send(msg)?,
+			SubsystemSender::Unbounded(tx) => tx.unbounded_send(msg)?,
+		}
+
+		Ok(())
+	}
+}
+
+/// A context type that is given to the [`Subsystem`] upon spawning.
+/// It can be used by [`Subsystem`] to communicate with other [`Subsystem`]s
+/// or to spawn it's [`SubsystemJob`]s.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`SubsystemJob`]: trait.SubsystemJob.html
+pub struct SubsystemContext<M: Debug>{
+	id: SubsystemId,
+	rx: Either<mpsc::Receiver<Envelope<M>>, mpsc::UnboundedReceiver<Envelope<M>>>,
+	signals: mpsc::UnboundedReceiver<OverseerSignal>,
+	tx: mpsc::Sender<ToOverseer>,
+	posts: mpsc::UnboundedSender<ToOverseer>,
+	spawner: Arc<dyn Spawn + Send + Sync>,
+	metrics: Arc<Metrics>,
+	blocking_pool: Arc<dyn SpawnBlocking>,
+	clock: Arc<dyn Clock>,
+	cancelled: Shared<oneshot::Receiver<()>>,
+	concluded: Arc<AtomicBool>,
+	current_message_id: Option<MessageId>,
+	// The span of the last message received, the messages sent meanwhile follow from it.
+	span: MessageSpan,
+}
+
+/// A signal used by [`Overseer`] to communicate with the [`Subsystem`]s.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub enum OverseerSignal {
+	/// `Subsystem` should start working.
+	StartWork,
+	/// `Subsystem` should stop working.
+	StopWork,
+	/// Conclude the work of the `Overseer` and all `Subsystem`s.
+	Conclude,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+/// A message type used by the Validation [`Subsystem`].
+///
+/// [`Subsystem`]: trait.Subsystem.html
+pub enum ValidationSubsystemMessage {
+	ValidityAttestation,
+	/// A request to validate a candidate, answered with whether it is valid.
+	///
+	/// The `Responder` is left out of the serialized request, so a deserialized one is answered to nobody.
+	Validate(#[cfg_attr(feature = "record", serde(skip, default = "Responder::detached"))] Responder<bool>),
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "record", derive(Clone, Serialize, Deserialize))]
+/// A message type used by the CandidateBacking [`Subsystem`].
+///
+/// [`Subsystem`]: trait.Subsystem.html
+pub enum CandidateBackingSubsystemMessage {
+	RegisterBackingWatcher,
+	Second,
+}
+
+/// A message type tying together all message types that are used across [`Subsystem`]s.
+///
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub enum AllMessages {
+	Validation(ValidationSubsystemMessage),
+	CandidateBacking(CandidateBackingSubsystemMessage),
+}
+
+#[cfg(feature = "record")]
+impl AllMessages {
+	// A copy of the message for the recorder. The `Responder` of a request stays with
+	// the message to be answered, the copy gets one that answers to nobody.
+	fn without_responder(&self) -> Self {
+		match self {
+			AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation) => {
+				AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)
+			}
+			AllMessages::Validation(ValidationSubsystemMessage::Validate(_)) => {
+				AllMessages::Validation(ValidationSubsystemMessage::Validate(Responder::detached()))
+			}
+			AllMessages::CandidateBacking(msg) => AllMessages::CandidateBacking(msg.clone()),
+		}
+	}
+}
+
+/// The answering side of a request, carried by the message of the request.
+///
+/// The sender of the request gets one by [`SubsystemContext::send_and_recv`] and waits for
+/// the answer, which the receiver sends with [`Responder::send`]. If the `Responder` is
+/// dropped without an answer the sender gets `SubsystemError::ChannelClosed`.
+///
+/// A recorded request is recorded without its `Responder`, so a replayed one is answered to nobody.
+///
+/// [`SubsystemContext::send_and_recv`]: struct.SubsystemContext.html#method.send_and_recv
+/// [`Responder::send`]: struct.Responder.html#method.send
+pub struct Responder<R>(oneshot::Sender<R>);
+
+impl<R> Responder<R> {
+	/// Answer the request.
+	///
+	/// Returns `SubsystemError::ChannelClosed` if the sender does not wait for the answer anymore.
+	pub fn send(self, response: R) -> SubsystemResult<()> {
+		self.0.send(response).map_err(|_| SubsystemError::ChannelClosed)
+	}
+
+	// A `Responder` whose answer goes nowhere.
+	fn detached() -> Self {
+		Responder(oneshot::channel().0)
+	}
+}
+
+impl<R> From<oneshot::Sender<R>> for Responder<R> {
+	fn from(tx: oneshot::Sender<R>) -> Self {
+		Responder(tx)
+	}
+}
+
+impl<R> Debug for Responder<R> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "Responder(..)")
+	}
+}
+
+/// A message type some of whose messages are requests, carrying a [`Responder`].
+///
+/// [`Responder`]: struct.Responder.html
+pub trait Request {
+	/// What the requests are answered with.
+	type Response;
+
+	/// Take the `Responder` out of the message if it is a request, leaving one that answers to nobody.
+	fn take_responder(&mut self) -> Option<Responder<Self::Response>>;
+}
+
+impl Request for ValidationSubsystemMessage {
+	type Response = bool;
+
+	fn take_responder(&mut self) -> Option<Responder<bool>> {
+		match self {
+			ValidationSubsystemMessage::Validate(responder) => Some(std::mem::replace(responder, Responder::detached())),
+			ValidationSubsystemMessage::ValidityAttestation => None,
+		}
+	}
+}
+
+/// The kind of a message, to tell messages apart without their contents.
+pub trait MessageKind {
+	/// The name of the variant of the message.
+	fn kind(&self) -> &'static str;
+}
+
+impl MessageKind for ValidationSubsystemMessage {
+	fn kind(&self) -> &'static str {
+		match self {
+			ValidationSubsystemMessage::ValidityAttestation => "ValidityAttestation",
+			ValidationSubsystemMessage::Validate(_) => "Validate",
+		}
+	}
+}
+
+impl MessageKind for CandidateBackingSubsystemMessage {
+	fn kind(&self) -> &'static str {
+		match self {
+			CandidateBackingSubsystemMessage::RegisterBackingWatcher => "RegisterBackingWatcher",
+			CandidateBackingSubsystemMessage::Second => "Second",
+		}
+	}
+}
+
+impl MessageKind for AllMessages {
+	fn kind(&self) -> &'static str {
+		match self {
+			AllMessages::Validation(msg) => msg.kind(),
+			AllMessages::CandidateBacking(msg) => msg.kind(),
+		}
+	}
+}
+
+impl AllMessages {
+	// The `Subsystem` the message is routed to.
+	fn recipient(&self) -> SubsystemId {
+		match self {
+			AllMessages::Validation(_) => SubsystemId::Validation,
+			AllMessages::CandidateBacking(_) => SubsystemId::CandidateBacking,
+		}
+	}
+}
+
+/// Any of the [`Subsystem`]s of the [`Overseer`], to be spawned with [`OverseerHandler::spawn_subsystem`].
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+/// [`OverseerHandler::spawn_subsystem`]: struct.OverseerHandler.html#method.spawn_subsystem
+pub enum AnySubsystem {
+	Validation(Box<dyn Subsystem<ValidationSubsystemMessage> + Send>),
+	CandidateBacking(Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>),
+}
+
+impl AnySubsystem {
+	/// The place of the `Subsystem` in the `Overseer`.
+	pub fn id(&self) -> SubsystemId {
+		match self {
+			AnySubsystem::Validation(_) => SubsystemId::Validation,
+			AnySubsystem::CandidateBacking(_) => SubsystemId::CandidateBacking,
+		}
+	}
+}
+
+/// A message type that a [`Subsystem`] receives from the [`Overseer`].
+/// It wraps siglans from the [`Overseer`] and messages that are circulating
+/// between subsystems.
+///
+/// It is generic over over the message type `M` that a particular `Subsystem` may use.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+#[derive(Debug)]
+#[cfg_attr(feature = "record", derive(Serialize, Deserialize))]
+pub enum FromOverseer<M: Debug> {
+	/// Signal from the `Overseer`.
+	Signal(OverseerSignal),
+
+	/// Some other `Subsystem`'s message.
+	Communication {
+		msg: M,
+	},
+}
+
+impl<M: Debug> SubsystemContext<M> {
+	/// Try to asyncronously receive a message.
+	///
+	/// This has to be used with caution, if you loop over this without
+	/// using `pending!()` macro you will end up with a busy loop!
+	pub async fn try_recv(&mut self) -> Result<Option<FromOverseer<M>>, ()> {
+		match poll!(future::poll_fn(|cx| self.poll_recv(cx))) {
+			Poll::Ready(Some(msg)) => Ok(Some(msg)),
+			Poll::Ready(None) => Err(()),
+			Poll::Pending => Ok(None),
+		}
+	}
+
+	/// Receive a message.
+	///
+	/// Signals are received ahead of any messages that are still queued.
+	pub async fn recv(&mut self) -> SubsystemResult<FromOverseer<M>> {
+		future::poll_fn(|cx| self.poll_recv(cx)).await.ok_or(SubsystemError::ChannelClosed)
+	}
+
+	/// Receive a message along with the [`Responder`] to answer it with, if it is a request.
+	///
+	/// [`Responder`]: struct.Responder.html
+	pub async fn recv_request(&mut self) -> SubsystemResult<(FromOverseer<M>, Option<Responder<M::Response>>)>
+	where
+		M: Request,
+	{
+		let mut msg = self.recv().await?;
+		let responder = match msg {
+			FromOverseer::Communication { ref mut msg } => msg.take_responder(),
+			FromOverseer::Signal(_) => None,
+		};
+
+		Ok((msg, responder))
+	}
+
+	/// Receive a message, giving up if none arrives in time.
+	///
+	/// Returns `SubsystemError::Timeout` if nothing has been received within `timeout`
+	/// and `SubsystemError::ChannelClosed` if the `Overseer` has dropped the channel.
+	pub async fn recv_timeout(&mut self, timeout: Duration) -> SubsystemResult<FromOverseer<M>> {
+		let timeout = self.clock.sleep(timeout);
+		let recv = future::poll_fn(|cx| self.poll_recv(cx));
+
+		match future::select(recv, timeout).await {
+			Either::Left((msg, _)) => msg.ok_or(SubsystemError::ChannelClosed),
+			Either::Right(_) => Err(SubsystemError::Timeout),
+		}
+	}
+
+	/// Receive a signal, ignoring any other messages.
+	///
+	/// Signals are sent on a separate channel, so this can be used to learn
+	/// about a `Conclude` even while the messages are not being processed.
+	pub async fn recv_signal(&mut self) -> SubsystemResult<OverseerSignal> {
+		self.signals.next().await.ok_or(SubsystemError::ChannelClosed)
+	}
+
+	/// Receive the messages as a `Stream`, to be `select!`ed over together with other streams.
+	///
+	/// The stream yields what [`recv`] would, and ends once the `Overseer`
+	/// has dropped its side of the channel.
+	///
+	/// [`recv`]: #method.recv
+	pub fn recv_stream(&mut self) -> impl FusedStream<Item = FromOverseer<M>> + Unpin + '_ {
+		stream::poll_fn(move |cx| self.poll_recv(cx)).fuse()
+	}
+
+	/// Spawn a child task on the executor.
+	pub async fn spawn(&mut self, s: Pin<Box<dyn Future<Output = ()> + Send>>) -> SubsystemResult<()> {
+		let (tx, rx) = oneshot::channel();
+		self.tx.send(ToOverseer::SpawnJob {
+			s,
+			res: tx,
+		}).await?;
+
+		rx.await?
+	}
+
+	/// Spawn a child task on the executor, giving up if the `Overseer` does not answer in time.
+	///
+	/// Returns `SubsystemError::Timeout` if the request to spawn has not been answered
+	/// within `timeout`, in which case the task may or may not have been spawned.
+	pub async fn spawn_timeout(
+		&mut self,
+		s: Pin<Box<dyn Future<Output = ()> + Send>>,
+		timeout: Duration,
+	) -> SubsystemResult<()> {
+		let timeout = self.clock.sleep(timeout);
+
+		match future::select(Box::pin(self.spawn(s)), timeout).await {
+			Either::Left((res, _)) => res,
+			Either::Right(_) => Err(SubsystemError::Timeout),
+		}
+	}
+
+	/// Wait until the `Overseer` asks for the work of this `Subsystem` to be cancelled.
+	///
+	/// This happens when the `Overseer` stops or the `Subsystem` is restarted or aborted,
+	/// so long operations can be raced against it. The returned future does not borrow
+	/// the context and can be moved into spawned jobs.
+	///
+	/// The `Subsystem` itself is dropped along with the `Overseer`, but the tasks it
+	/// has spawned are not. This resolves when the task of the `Overseer` is dropped too,
+	/// so those tasks can shut themselves down once orphaned.
+	pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+		self.cancelled.clone().map(|_| ())
+	}
+
+	/// Whether the `Overseer` is still there to receive what this `Subsystem` sends.
+	pub fn is_overseer_alive(&self) -> bool {
+		!self.tx.is_closed()
+	}
+
+	/// The [`Clock`] of the `Overseer`, for the `Subsystem` to time its work with.
+	///
+	/// Timing with it rather than with the system clock lets tests control time.
+	///
+	/// [`Clock`]: trait.Clock.html
+	pub fn clock(&self) -> Arc<dyn Clock> {
+		self.clock.clone()
+	}
+
+	/// Conclude the work of this `Subsystem` for good.
+	///
+	/// The `Subsystem` should return what this returns. The `Overseer` is told right
+	/// away and drops the messages sent to the `Subsystem` from then on, as they would
+	/// never be received. Once the `Subsystem` has finished it is removed without applying
+	/// the exit policy, with an [`OverseerEvent::SubsystemStopped`]. One that returns
+	/// `Ok(())` without concluding is removed too, but with an [`OverseerEvent::SubsystemExited`].
+	///
+	/// [`OverseerEvent::SubsystemStopped`]: enum.OverseerEvent.html#variant.SubsystemStopped
+	/// [`OverseerEvent::SubsystemExited`]: enum.OverseerEvent.html#variant.SubsystemExited
+	pub fn conclude(self) -> SubsystemResult<()> {
+		// The flag is set first, so that the exit of the subsystem is told apart
+		// even when the overseer learns of it before it gets this message.
+		self.concluded.store(true, Ordering::Relaxed);
+		let _ = self.posts.unbounded_send(ToOverseer::Concluded);
+
+		Ok(())
+	}
+
+	/// Spawn a short-lived task straight onto the executor.
+	///
+	/// Unlike a `Subsystem` the task is not watched by the `Overseer`,
+	/// so nothing happens when it finishes.
+	pub fn spawn_task(&self, f: impl Future<Output = ()> + Send + 'static) -> SubsystemResult<()> {
+		self.spawner.spawn(f).map_err(Into::into)
+	}
+
+	/// The executor the `Overseer` runs its `Subsystem`s on.
+	///
+	/// It can be cloned and moved into spawned tasks, or given to libraries that
+	/// spawn tasks of their own. As with [`spawn_task`], nothing spawned with it is
+	/// watched by the `Overseer`.
+	///
+	/// [`spawn_task`]: #method.spawn_task
+	pub fn spawner(&self) -> impl Spawn + Clone + Send + Sync + 'static {
+		SharedSpawner(self.spawner.clone())
+	}
+
+	/// Run blocking code on the blocking pool of the `Overseer` and get its result.
+	///
+	/// Unlike the jobs spawned with `spawn` this does not stall the executor.
+	pub fn spawn_blocking<T, F>(&self, f: F) -> impl Future<Output = SubsystemResult<T>>
+	where
+		T: Send + 'static,
+		F: FnOnce() -> T + Send + 'static,
+	{
+		let (tx, rx) = oneshot::channel();
+		self.blocking_pool.spawn_blocking(Box::new(move || {
+			let _ = tx.send(f());
+		}));
+
+		rx.map(|res| res.map_err(Into::into))
+	}
+
+	/// Send a direct message to some other `Subsystem`, routed based on message type.
+	///
+	/// The message is recorded as caused by the last message received. With the
+	/// `tracing` feature it is sent in the current span, or else in the span of
+	/// the last message received.
+	///
+	/// The messages one `Subsystem` sends to another one with the same [`Priority`]
+	/// are received in the order they have been sent. There is no such guarantee
+	/// for messages of different senders.
+	///
+	/// This waits for room in the bounded channel to the `Overseer`, so a `Subsystem`
+	/// sending faster than the `Overseer` dispatches is slowed down to its pace. Use
+	/// [`post_msg`] to send without waiting.
+	///
+	/// [`Priority`]: enum.Priority.html
+	/// [`post_msg`]: #method.post_msg
+	pub async fn send_msg(&mut self, msg: AllMessages) -> SubsystemResult<()> {
+		self.send_msg_with_priority(msg, Priority::Normal).await
+	}
+
+	/// Send a direct message to some other `Subsystem` with the given [`Priority`].
+	///
+	/// [`Priority`]: enum.Priority.html
+	pub async fn send_msg_with_priority(&mut self, msg: AllMessages, priority: Priority) -> SubsystemResult<()> {
+		self.tx.send(ToOverseer::SubsystemMessage {
+			msg,
+			causation_id: self.current_message_id,
+			priority,
+			deadline: None,
+			span: self.span.current(),
+		}).await?;
+
+		Ok(())
+	}
+
+	/// Send a direct message to some other `Subsystem` that is dropped if it has not
+	/// been dispatched by `deadline`.
+	///
+	/// The `deadline` is checked against the [`clock`] of the `Overseer` as the message
+	/// is dispatched, and an expired message is reported with [`OverseerEvent::MessageExpired`].
+	///
+	/// [`clock`]: #method.clock
+	/// [`OverseerEvent::MessageExpired`]: enum.OverseerEvent.html#variant.MessageExpired
+	pub async fn send_msg_with_deadline(&mut self, msg: AllMessages, deadline: Instant) -> SubsystemResult<()> {
+		self.tx.send(ToOverseer::SubsystemMessage {
+			msg,
+			causation_id: self.current_message_id,
+			priority: Priority::Normal,
+			deadline: Some(deadline),
+			span: self.span.current(),
+		}).await?;
+
+		Ok(())
+	}
+
+	/// Send a direct message to some other `Subsystem` without waiting for room in the channel.
+	///
+	/// If the channel to the `Overseer` is full or closed the message is handed back,
+	/// so that it can be dropped, buffered or sent again later.
+	pub fn try_send_msg(&mut self, msg: AllMessages) -> Result<(), TrySendError> {
+		let res = self.tx.try_send(ToOverseer::SubsystemMessage {
+			msg,
+			causation_id: self.current_message_id,
+			priority: Priority::Normal,
+			deadline: None,
+			span: self.span.current(),
+		});
+
+		res.map_err(|e| {
+			let error = if e.is_full() {
+				SubsystemError::MailboxFull
+			} else {
+				SubsystemError::ChannelClosed
+			};
+
+			match e.into_inner() {
+				ToOverseer::SubsystemMessage { msg, .. } => TrySendError { error, msg },
+				_ => unreachable!("a `SubsystemMessage` has been sent"),
+			}
+		})
+	}
+
+	/// Send a direct message to some other `Subsystem` without waiting at all.
+	///
+	/// The message is put on an unbounded channel to the `Overseer` of its own, so this
+	/// never waits and only fails with `SubsystemError::ChannelClosed` once the `Overseer`
+	/// is gone. The messages posted are received in the order they have been posted, but
+	/// not in order with those sent with [`send_msg`] and the other methods: a posted
+	/// message may overtake a sent one or fall behind it. They are sent with
+	/// `Priority::Normal`.
+	///
+	/// Nothing slows down a `Subsystem` posting faster than the `Overseer` dispatches,
+	/// the posted messages pile up instead. So this is for the occasional notification
+	/// that is not worth waiting for, rather than for a stream of messages.
+	///
+	/// [`send_msg`]: #method.send_msg
+	pub fn post_msg(&self, msg: AllMessages) -> SubsystemResult<()> {
+		self.posts.unbounded_send(ToOverseer::SubsystemMessage {
+			msg,
+			causation_id: self.current_message_id,
+			priority: Priority::Normal,
+			deadline: None,
+			span: self.span.current(),
+		}).map_err(|_| SubsystemError::ChannelClosed)
+	}
+
+	/// Tell the `Overseer` that this `Subsystem` is ready to be depended on.
+	///
+	/// With [`OverseerConfig::ready_timeout`] set the `Subsystem`s that depend on
+	/// this one are started only after this has been called.
+	///
+	/// [`OverseerConfig::ready_timeout`]: struct.OverseerConfig.html#structfield.ready_timeout
+	pub async fn signal_ready(&mut self) -> SubsystemResult<()> {
+		self.tx.send(ToOverseer::Ready).await?;
+
+		Ok(())
+	}
+
+	/// Tell the `Overseer` that this `Subsystem` is alive.
+	///
+	/// With [`OverseerConfig::liveness_timeout`] set this has to be called
+	/// periodically for the `Subsystem` to be considered healthy.
+	///
+	/// [`OverseerConfig::liveness_timeout`]: struct.OverseerConfig.html#structfield.liveness_timeout
+	pub async fn heartbeat(&mut self) -> SubsystemResult<()> {
+		self.tx.send(ToOverseer::Heartbeat).await?;
+
+		Ok(())
+	}
+
+	/// Ask the `Overseer` which other `Subsystem`s are running right now.
+	///
+	/// The answer is not cached, so each call takes a round trip through the `Overseer`.
+	pub async fn peers(&mut self) -> SubsystemResult<Vec<SubsystemId>> {
+		let (tx, rx) = oneshot::channel();
+		self.tx.send(ToOverseer::Peers(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// Send a message to some other `Subsystem` and wait for its reply.
+	///
+	/// `make_msg` embeds the sending side of a `oneshot` channel into the message,
+	/// usually as a [`Responder`], and the receiving `Subsystem` answers through it.
+	/// If it drops the sender without answering this returns `SubsystemError::ChannelClosed`.
+	///
+	/// [`Responder`]: struct.Responder.html
+	pub async fn send_and_recv<R>(
+		&mut self,
+		make_msg: impl FnOnce(oneshot::Sender<R>) -> AllMessages,
+	) -> SubsystemResult<R> {
+		let (tx, rx) = oneshot::channel();
+		self.send_msg(make_msg(tx)).await?;
+
+		Ok(rx.await?)
+	}
+
+	/// The identifier this `Subsystem` is running under.
+	pub fn id(&self) -> SubsystemId {
+		self.id
+	}
+
+	/// The identifier of the last message received, `None` if that was a signal.
+	pub fn current_message_id(&self) -> Option<MessageId> {
+		self.current_message_id
+	}
+
+	/// The tracing span of the last message received, disabled if that was a signal.
+	///
+	/// The span follows from the one the message has been sent in, and the messages
+	/// sent until the next one is received are sent in it, unless the `Subsystem` has
+	/// entered a span of its own. A `Subsystem` enters it, or instruments the handling
+	/// of the message with it, to have what it does for the message recorded in there:
+	///
+	/// ```ignore
+	/// let msg = ctx.recv().await?;
+	/// let span = ctx.span().clone();
+	/// handle(&mut ctx, msg).instrument(span).await
+	/// ```
+	#[cfg(feature = "tracing")]
+	pub fn span(&self) -> &tracing::Span {
+		&self.span.0
+	}
+
+	fn poll_recv(&mut self, cx: &mut Context) -> Poll<Option<FromOverseer<M>>> {
+		if let Poll::Ready(Some(signal)) = self.signals.poll_next_unpin(cx) {
+			self.current_message_id = None;
+			self.span = MessageSpan::none();
+			return Poll::Ready(Some(FromOverseer::Signal(signal)));
+		}
+
+		match self.rx.poll_next_unpin(cx) {
+			Poll::Ready(Some(Envelope { id, msg, span })) => {
+				if let FromOverseer::Communication { .. } = msg {
+					self.metrics.subsystem(self.id).received.fetch_add(1, Ordering::Relaxed);
+					self.metrics.received.wake();
+					self.span = span.received(self.id, id);
+				} else {
+					self.span = MessageSpan::none();
+				}
+
+				self.current_message_id = id;
+				Poll::Ready(Some(msg))
+			}
+			Poll::Ready(None) => Poll::Ready(None),
+			Poll::Pending => Poll::Pending,
+		}
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn new(
+		id: SubsystemId,
+		rx: Either<mpsc::Receiver<Envelope<M>>, mpsc::UnboundedReceiver<Envelope<M>>>,
+		signals: mpsc::UnboundedReceiver<OverseerSignal>,
+		tx: mpsc::Sender<ToOverseer>,
+		posts: mpsc::UnboundedSender<ToOverseer>,
+		spawner: Arc<dyn Spawn + Send + Sync>,
+		metrics: Arc<Metrics>,
+		blocking_pool: Arc<dyn SpawnBlocking>,
+		clock: Arc<dyn Clock>,
+		cancelled: oneshot::Receiver<()>,
+	) -> Self {
+		Self {
+			id,
+			rx,
+			signals,
+			tx,
+			posts,
+			spawner,
+			metrics,
+			blocking_pool,
+			clock,
+			cancelled: cancelled.shared(),
+			concluded: Arc::new(AtomicBool::new(false)),
+			current_message_id: None,
+			span: MessageSpan::none(),
+		}
+	}
+}
+
+/// A trait that describes the [`Subsystem`]s that can run on the [`Overseer`].
+///
+/// It is generic over the message type circulating in the system.
+/// The idea that we want some type contaning persistent state that
+/// can spawn actually running subsystems when asked to.
+///
+/// [`Overseer`]: struct.Overseer.html
+/// [`Subsystem`]: trait.Subsystem.html
+pub trait Subsystem<M: Debug> {
+	/// Start this `Subsystem` and return `SpawnedSubsystem`.
+	fn start(&mut self, ctx: SubsystemContext<M>) -> SpawnedSubsystem;
+
+	/// Called by the `Overseer` right before each call to `start`.
+	///
+	/// A place to acquire what the future returned by `start` is going to use.
+	fn on_start(&mut self) {}
+
+	/// Called by the `Overseer` once the future returned by `start` has finished,
+	/// whether it has concluded or failed, or once it has been dropped when the
+	/// `Overseer` stops. So it is called once for each call to `on_start`, before
+	/// the `Subsystem` is restarted or replaced.
+	///
+	/// A place to release what has been acquired in `on_start`.
+	fn on_stop(&mut self) {}
+}
+
+/// A [`Subsystem`] made of a closure that returns the future to run.
+///
+/// The closure is called each time the `Subsystem` is (re)started.
+///
+/// ```
+/// # use overseer::{FnSubsystem, FromOverseer, OverseerSignal, SubsystemContext, ValidationSubsystemMessage};
+/// let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+///     loop {
+///         match ctx.recv().await? {
+///             FromOverseer::Communication { msg } => println!("received {:?}", msg),
+///             FromOverseer::Signal(OverseerSignal::Conclude) => return ctx.conclude(),
+///             FromOverseer::Signal(_) => (),
+///         }
+///     }
+/// });
+/// ```
+///
+/// [`Subsystem`]: trait.Subsystem.html
+pub struct FnSubsystem<F>(F);
+
+impl<F> FnSubsystem<F> {
+	/// Create a `Subsystem` that runs the future returned by `f`.
+	pub fn new(f: F) -> Self {
+		FnSubsystem(f)
+	}
+}
+
+impl<M, F, Fut> Subsystem<M> for FnSubsystem<F>
+where
+	M: Debug,
+	F: FnMut(SubsystemContext<M>) -> Fut,
+	Fut: Future<Output = SubsystemResult<()>> + Send + 'static,
+{
+	fn start(&mut self, ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+		SpawnedSubsystem((self.0)(ctx).boxed())
+	}
+}
+
+/// A subsystem that we oversee.
+///
+/// Ties together the [`Subsystem`] itself and it's running instance
+/// (which may be missing if the [`Subsystem`] is not running at the moment
+/// for whatever reason).
+///
+/// [`Subsystem`]: trait.Subsystem.html
+struct OverseenSubsystem<M: Debug> {
+	subsystem: Box<dyn Subsystem<M> + Send>,
+	instance: Option<SubsystemInstance<M>>,
+	channels: ChannelConfig,
+}
+
+// Takes the place of the subsystems of an `Overseer` whose state has been moved out.
+struct Taken;
+
+impl<M: Debug> Subsystem<M> for Taken {
+	fn start(&mut self, _: SubsystemContext<M>) -> SpawnedSubsystem {
+		SpawnedSubsystem(Box::pin(future::pending()))
+	}
+}
+
+/// The `Overseer` itself.
+///
+/// The `Overseer` is a `Future` that runs until it is stopped, see [`run`].
+///
+/// [`run`]: struct.Overseer.html#method.run
+pub struct Overseer<S: Spawn> {
+	/// A validation subsystem
+	validation_subsystem: OverseenSubsystem<ValidationSubsystemMessage>,
+
+	/// A candidate backing subsystem
+	candidate_backing_subsystem: OverseenSubsystem<CandidateBackingSubsystemMessage>,
+
+	/// Spawner to spawn tasks to, shared with the subsystems for their tasks.
+	s: Arc<S>,
+
+	/// Here we keep handles to spawned subsystems to be notified when they terminate.
+	///
+	/// Each handle comes with the generation of the subsystem it has been spawned as,
+	/// which is the number of times it has been started by then.
+	running_subsystems: FuturesUnordered<BoxFuture<'static, (SubsystemId, usize, SubsystemResult<()>)>>,
+
+	/// Gather running subsystms' outbound streams into one.
+	running_subsystems_rx: StreamUnordered<BoxStream<'static, (SubsystemId, ToOverseer)>>,
+
+	/// Events that are sent to the overseer from the outside world
+	events_rx: mpsc::Receiver<Event>,
+
+	/// Configuration of this overseer.
+	config: OverseerConfig,
+
+	/// Number of times each subsystem has been restarted by the `restart_policy`.
+	restarts: HashMap<SubsystemId, usize>,
+
+	/// Subsystems waiting for their restart delay to pass.
+	pending_restarts: FuturesUnordered<BoxFuture<'static, SubsystemId>>,
+
+	/// Subscribers to the `OverseerEvent`s.
+	event_subscribers: Vec<mpsc::Sender<OverseerEvent>>,
+
+	/// Number of messages that did not fit into the `dead_letters` channel.
+	dropped_dead_letters: usize,
+
+	/// Whether the overseer is waiting for the subsystems to finish before exiting.
+	draining: bool,
+
+	/// Subsystems that have signaled they are ready.
+	ready: HashSet<SubsystemId>,
+
+	/// Callers waiting for some subsystem to become ready.
+	ready_waiters: Vec<(SubsystemId, oneshot::Sender<()>)>,
+
+	/// Subsystems waiting for their dependencies to become ready.
+	pending_starts: Vec<SubsystemId>,
+
+	/// Deadlines for the dependencies of the `pending_starts` to become ready.
+	start_timeouts: FuturesUnordered<BoxFuture<'static, SubsystemId>>,
+
+	/// When each running subsystem has last sent a heartbeat.
+	heartbeats: HashMap<SubsystemId, Instant>,
+
+	/// Running subsystems that have missed their heartbeats.
+	unhealthy: HashSet<SubsystemId>,
+
+	/// Running subsystems whose mailbox is above its high water mark.
+	lagging: HashSet<SubsystemId>,
+
+	/// The next check of the `heartbeats`.
+	health_check: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	/// Counters of the messages passing through the overseer.
+	metrics: Arc<Metrics>,
+
+	/// Where the subsystems run their blocking work.
+	blocking_pool: Arc<dyn SpawnBlocking>,
+
+	/// Where the time is taken from.
+	clock: Arc<dyn Clock>,
+
+	/// The identifier of the next dispatched message.
+	next_message_id: u64,
+
+	/// Messages from the subsystems waiting to be dispatched, by their `Priority`.
+	queued: [VecDeque<QueuedMessage>; 3],
+
+	/// How many times in a row the queue of each `Priority` has been passed over.
+	passed_over: [usize; 3],
+
+	/// How many messages have been taken from each subsystem in the current pass,
+	/// kept around so that a pass does not allocate.
+	taken: HashMap<SubsystemId, usize>,
+
+	/// The result each subsystem has last finished with.
+	results: HashMap<SubsystemId, SubsystemResult<()>>,
+
+	/// Callers waiting for some subsystem to finish.
+	completion_waiters: Vec<(SubsystemId, oneshot::Sender<SubsystemResult<()>>)>,
+
+	/// The order in which the subsystems have been started.
+	boot_order: Vec<SubsystemId>,
+
+	/// Subsystems stopped to be restarted along with a failed one, started again once they have finished.
+	restarting: HashSet<SubsystemId>,
+
+	/// Subsystems concluding to be replaced, along with their replacement and the caller waiting for it to start.
+	replacing: HashMap<SubsystemId, (AnySubsystem, oneshot::Sender<SubsystemResult<SubsystemId>>)>,
+
+	/// Counts the steps of the overseer for the watchdog, along with the deadline of the watchdog.
+	watchdog: Option<(Arc<AtomicU64>, Duration)>,
+
+	/// Wakes the overseer up, so that it makes progress for the watchdog while idle.
+	watchdog_ticks: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	/// Called by the watchdog on each stall, once it is started along with the `run` loop.
+	on_stall: Option<Box<dyn Fn() + Send>>,
+
+	/// Fires once the subsystems have been draining for the `drain_timeout`.
+	drain_deadline: FuturesUnordered<BoxFuture<'static, ()>>,
+
+	/// How many times each subsystem has been started.
+	starts: HashMap<SubsystemId, usize>,
+
+	/// When a message has last been dispatched to each subsystem.
+	last_dispatch: HashMap<SubsystemId, Instant>,
+
+	/// Subsystems whose messages are kept in their overflow instead of being delivered.
+	paused: HashSet<SubsystemId>,
+
+	/// What every message is passed through before it is dispatched, in order.
+	middleware: Vec<Middleware>,
+
+	/// The name of the overseer in the logs, if it has one.
+	name: Option<&'static str>,
+
+	/// The names of the subsystems in the logs, along with their ids.
+	names: HashMap<SubsystemId, &'static str>,
+
+	/// How long the loop has spent on each of the subsystems.
+	#[cfg(feature = "timing")]
+	timings: LoopTimings,
+
+	/// A message from the outside world taken while over the `message_budget`,
+	/// yielded once the mailboxes have drained.
+	held_external: Option<BoxFuture<'static, AllMessages>>,
+
+	/// The last dispatched messages, up to the `dispatch_log_capacity`.
+	dispatch_log: VecDeque<DispatchEntry>,
+
+	/// Why the overseer is stopping, once it is.
+	shutdown: Option<ShutdownReport>,
+
+	/// The tokens of the subsystems with a rate limit, along with when they have last been refilled.
+	rate_buckets: HashMap<SubsystemId, (f64, Instant)>,
+
+	/// Messages from subsystems over their rate limit, by their `Priority`, kept until it allows for them.
+	throttled: HashMap<SubsystemId, VecDeque<(Priority, QueuedMessage)>>,
+
+	/// Wakes the overseer up once a subsystem with kept messages has a token again.
+	throttle_ticks: FuturesUnordered<BoxFuture<'static, SubsystemId>>,
+
+	/// The `run` loop, once the `Overseer` has been polled as a `Future`.
+	running: Option<future::Fuse<BoxFuture<'static, ShutdownReport>>>,
+}
+
+// Looks at a message on its way from a subsystem, or the outside world, to another one.
+type Middleware = Box<dyn Fn(Option<SubsystemId>, SubsystemId, AllMessages) -> Option<AllMessages> + Send>;
+
+// A message from a subsystem waiting to be dispatched.
+struct QueuedMessage {
+	from: SubsystemId,
+	causation_id: Option<MessageId>,
+	msg: AllMessages,
+	deadline: Option<Instant>,
+	span: MessageSpan,
+}
+
+impl<S> Overseer<S>
+where
+	S: Spawn + Send + Sync + 'static,
+{
+	/// Create a new intance of the `Overseer` with a fixed set of [`Subsystem`]s.
+	///
+	/// Each [`Subsystem`] is passed to this function as an explicit parameter
+	/// and is supposed to implement some interface that is generic over message type
+	/// that is specific to this [`Subsystem`]. At the moment there are only two
+	/// subsystems:
+	///   * Validation
+	///   * CandidateBacking
+	///
+	/// As any entity that satisfies the interface may act as a [`Subsystem`] this allows
+	/// mocking in the test code:
+	///
+	/// ```text
+	///                  +------------------------------------+
+	///                  |            Overseer                |
+	///                  +------------------------------------+
+	///                    /            |             |      \
+	///      ................. subsystems...................................
+	///      . +-----------+    +-----------+   +----------+   +---------+ .
+	///      . |           |    |           |   |          |   |         | .
+	///      . +-----------+    +-----------+   +----------+   +---------+ .
+	///      ...............................................................
+	///                              |
+	///                        probably `spawn`
+	///                            a `job`
+	///                              |
+	///                              V
+	///                         +-----------+
+	///                         |           |
+	///                         +-----------+
+	///
+	/// ```
+	///
+	/// [`Subsystem`]: trait.Subsystem.html
+	///
+	/// # Example
+	///
+	/// The [`Subsystems`] may be any type as long as they implement an expected interface.
+	/// Here, we create two mock subsystems and start the `Overseer` with them. For the sake
+	/// of simplicity the termination of the example is done with a timeout.
+	/// ```
+	/// # use std::time::Duration;
+	/// # use futures::{executor, pin_mut, select, FutureExt};
+	/// # use futures_timer::Delay;
+	/// # use overseer::{
+	/// #     Overseer, Subsystem, SpawnedSubsystem, SubsystemContext,
+	/// #     ValidationSubsystemMessage, CandidateBackingSubsystemMessage,
+	/// # };
+	///
+	/// struct ValidationSubsystem;
+	/// impl Subsystem<ValidationSubsystemMessage> for ValidationSubsystem {
+	///     fn start(
+	///         &mut self,
+	///         mut ctx: SubsystemContext<ValidationSubsystemMessage>,
+	///     ) -> SpawnedSubsystem {
+	///         SpawnedSubsystem(Box::pin(async move {
+	///             loop {
+	///                 Delay::new(Duration::from_secs(1)).await;
+	///             }
+	///         }))
+	///     }
+	/// }
+	///
+	/// struct CandidateBackingSubsystem;
+	/// impl Subsystem<CandidateBackingSubsystemMessage> for CandidateBackingSubsystem {
+	///     fn start(
+	///         &mut self,
+	///         mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>,
+	///     ) -> SpawnedSubsystem {
+	///         SpawnedSubsystem(Box::pin(async move {
+	///             loop {
+	///                 Delay::new(Duration::from_secs(1)).await;
+	///             }
+	///         }))
+	///     }
+	/// }
+	///
+	/// # fn main() { executor::block_on(async move {
+	/// let spawner = executor::ThreadPool::new().unwrap();
+	/// let (overseer, _handler) = Overseer::new(
+	///     Box::new(ValidationSubsystem),
+	///     Box::new(CandidateBackingSubsystem),
+	///     spawner,
+	/// ).unwrap();
+	///
+	/// let timer = Delay::new(Duration::from_millis(50)).fuse();
+	///
+	/// let overseer_fut = overseer.run().fuse();
+	/// pin_mut!(timer);
+	/// pin_mut!(overseer_fut);
+	///
+	/// select! {
+	///     _ = overseer_fut => (),
+	///     _ = timer => (),
+	/// }
+	/// #
+	/// # }); }
+	/// ```
+	pub fn new(
+		validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send>,
+		candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>,
+		s: S,
+	) -> SubsystemResult<(Self, OverseerHandler)> {
+		Self::with_config(validation, candidate_backing, s, OverseerConfig::default())
+	}
+
+	/// Create a new instance of the `Overseer` with the given [`OverseerConfig`].
+	///
+	/// See [`new`] for the details.
+	///
+	/// [`OverseerConfig`]: struct.OverseerConfig.html
+	/// [`new`]: #method.new
+	pub fn with_config(
+		validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send>,
+		candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>,
+		s: S,
+		config: OverseerConfig,
+	) -> SubsystemResult<(Self, OverseerHandler)> {
+		Self::boot(validation, candidate_backing, s, config, &SubsystemId::ALL, HashMap::new())
+	}
+
+	/// Create a new instance of the `Overseer` from an [`OverseerTopology`] taken from another one.
+	///
+	/// `factory` is called with the id of each of the `Subsystem`s of the `topology`
+	/// and creates the `Subsystem` to start in its place. They are started in the order
+	/// of their dependencies, which replace the [`OverseerConfig::dependencies`], and keep
+	/// counting their restarts from where they were. A `Subsystem` that is not part of the
+	/// `topology` is not started, it can be spawned later with [`OverseerHandler::spawn_subsystem`].
+	///
+	/// # Panics
+	///
+	/// If `factory` creates a `Subsystem` for another place than the one it is called with.
+	///
+	/// [`OverseerTopology`]: struct.OverseerTopology.html
+	/// [`OverseerConfig::dependencies`]: struct.OverseerConfig.html#structfield.dependencies
+	/// [`OverseerHandler::spawn_subsystem`]: struct.OverseerHandler.html#method.spawn_subsystem
+	pub fn from_topology(
+		topology: OverseerTopology,
+		mut factory: impl FnMut(SubsystemId) -> AnySubsystem,
+		s: S,
+		mut config: OverseerConfig,
+	) -> SubsystemResult<(Self, OverseerHandler)> {
+		let mut validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send> = Box::new(Taken);
+		let mut candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send> = Box::new(Taken);
+
+		for id in &topology.subsystems {
+			let subsystem = factory(*id);
+			assert_eq!(subsystem.id(), *id, "factory created a subsystem for another place");
+
+			match subsystem {
+				AnySubsystem::Validation(s) => validation = s,
+				AnySubsystem::CandidateBacking(s) => candidate_backing = s,
+			}
+		}
+
+		// A start is counted as the subsystem is started, so its restarts come out as they were.
+		let starts = topology.restarts.iter()
+			.filter(|(id, _)| topology.subsystems.contains(id))
+			.map(|(id, restarts)| (*id, *restarts))
+			.collect();
+		config.dependencies = topology.dependencies;
+
+		Self::boot(validation, candidate_backing, s, config, &topology.subsystems, starts)
+	}
+
+	// Create a new instance of the `Overseer`, starting the given subsystems in the order of their dependencies.
+	fn boot(
+		validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send>,
+		candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>,
+		s: S,
+		config: OverseerConfig,
+		started: &[SubsystemId],
+		starts: HashMap<SubsystemId, usize>,
+	) -> SubsystemResult<(Self, OverseerHandler)> {
+		let (events_tx, events_rx) = mpsc::channel(config.events_capacity);
+
+		let metrics = Arc::new(Metrics::default());
+
+		let handler = OverseerHandler {
+			events_tx: events_tx.clone(),
+			metrics: metrics.clone(),
+			draining: false,
+		};
+
+		let boot_order = boot_order(&config.dependencies)?;
+
+		let blocking_pool = config.blocking_pool.clone().unwrap_or_else(|| Arc::new(LazyThreadPool::default()));
+
+		let mut this = Self::idle(
+			validation,
+			candidate_backing,
+			Arc::new(s),
+			config,
+			events_rx,
+			metrics,
+			blocking_pool,
+		);
+
+		this.starts = starts;
+		if let Some(timeout) = this.config.liveness_timeout {
+			this.health_check.push(this.clock.sleep(timeout));
+		}
+
+		for id in boot_order {
+			this.boot_order.push(id);
+			if !started.contains(&id) {
+				continue;
+			}
+
+			match this.config.ready_timeout {
+				Some(timeout) if this.dependencies(id).next().is_some() => {
+					this.pending_starts.push(id);
+					this.start_timeouts.push(this.clock.sleep(timeout).map(move |_| id).boxed());
+				}
+				_ => this.start_subsystem(id)?,
+			}
+		}
+
+		Ok((this, handler))
+	}
+
+	// An `Overseer` that has not started any of its subsystems.
+	fn idle(
+		validation: Box<dyn Subsystem<ValidationSubsystemMessage> + Send>,
+		candidate_backing: Box<dyn Subsystem<CandidateBackingSubsystemMessage> + Send>,
+		s: Arc<S>,
+		config: OverseerConfig,
+		events_rx: mpsc::Receiver<Event>,
+		metrics: Arc<Metrics>,
+		blocking_pool: Arc<dyn SpawnBlocking>,
+	) -> Self {
+		let validation_subsystem = OverseenSubsystem {
+			subsystem: validation,
+			instance: None,
+			channels: config.validation_channels.clone(),
+		};
+
+		let candidate_backing_subsystem = OverseenSubsystem {
+			subsystem: candidate_backing,
+			instance: None,
+			channels: config.candidate_backing_channels.clone(),
+		};
+
+		let clock = config.clock.clone().unwrap_or_else(|| Arc::new(SystemClock));
+		let dispatch_log = VecDeque::with_capacity(config.dispatch_log_capacity);
+
+		Self {
+			validation_subsystem,
+			candidate_backing_subsystem,
+			s,
+			running_subsystems: FuturesUnordered::new(),
+			running_subsystems_rx: StreamUnordered::new(),
+			events_rx,
+			config,
+			restarts: HashMap::new(),
+			pending_restarts: FuturesUnordered::new(),
+			event_subscribers: Vec::new(),
+			dropped_dead_letters: 0,
+			draining: false,
+			ready: HashSet::new(),
+			ready_waiters: Vec::new(),
+			pending_starts: Vec::new(),
+			start_timeouts: FuturesUnordered::new(),
+			heartbeats: HashMap::new(),
+			unhealthy: HashSet::new(),
+			lagging: HashSet::new(),
+			health_check: FuturesUnordered::new(),
+			metrics,
+			blocking_pool,
+			clock,
+			next_message_id: 0,
+			queued: Default::default(),
+			passed_over: [0; 3],
+			taken: HashMap::with_capacity(SubsystemId::ALL.len()),
+			results: HashMap::new(),
+			completion_waiters: Vec::new(),
+			boot_order: Vec::with_capacity(SubsystemId::ALL.len()),
+			restarting: HashSet::new(),
+			replacing: HashMap::new(),
+			watchdog: None,
+			watchdog_ticks: FuturesUnordered::new(),
+			on_stall: None,
+			drain_deadline: FuturesUnordered::new(),
+			starts: HashMap::new(),
+			last_dispatch: HashMap::new(),
+			paused: HashSet::new(),
+			middleware: Vec::new(),
+			name: None,
+			names: HashMap::new(),
+			#[cfg(feature = "timing")]
+			timings: LoopTimings::default(),
+			held_external: None,
+			dispatch_log,
+			shutdown: None,
+			rate_buckets: HashMap::new(),
+			throttled: HashMap::new(),
+			throttle_ticks: FuturesUnordered::new(),
+			running: None,
+		}
+	}
+
+	// Move the state out of this `Overseer`, leaving one behind that oversees nothing.
+	//
+	// The senders of the config go along with the state, so that they are closed once it is dropped.
+	fn take(&mut self) -> Self {
+		let dead_letters = self.config.dead_letters.take();
+		#[cfg(feature = "record")]
+		let recorder = self.config.recorder.take();
+
+		let (_, events_rx) = mpsc::channel(0);
+		let idle = Self::idle(
+			Box::new(Taken),
+			Box::new(Taken),
+			self.s.clone(),
+			self.config.clone(),
+			events_rx,
+			self.metrics.clone(),
+			self.blocking_pool.clone(),
+		);
+
+		let mut taken = std::mem::replace(self, idle);
+		taken.config.dead_letters = dead_letters;
+		#[cfg(feature = "record")]
+		{
+			taken.config.recorder = recorder;
+		}
+
+		taken
+	}
+
+	// What is left behind by `take` does not know anything about the `Overseer`.
+	fn assert_not_taken(&self) {
+		assert!(
+			self.running.is_none(),
+			"The `Overseer` is being run, it can only be inspected through an `OverseerHandler`",
+		);
+	}
+
+	/// Get the current values of the `Overseer` metrics.
+	///
+	/// Use [`OverseerHandler::metrics`] while the `Overseer` is running.
+	///
+	/// # Panics
+	///
+	/// If the `Overseer` has been polled, as it is then run elsewhere.
+	///
+	/// [`OverseerHandler::metrics`]: struct.OverseerHandler.html#method.metrics
+	pub fn metrics(&self) -> MetricsSnapshot {
+		self.assert_not_taken();
+		self.metrics.snapshot()
+	}
+
+	/// Describe the state of the `Overseer` in a human readable form, for the logs.
+	///
+	/// Each `Subsystem` is listed together with its dependencies.
+	/// Use [`OverseerHandler::debug_dump`] while the `Overseer` is running.
+	///
+	/// # Panics
+	///
+	/// If the `Overseer` has been polled, as it is then run elsewhere.
+	///
+	/// [`OverseerHandler::debug_dump`]: struct.OverseerHandler.html#method.debug_dump
+	pub fn debug_dump(&self) -> String {
+		self.assert_not_taken();
+		let mut dump = String::new();
+		let queued: usize = self.queued.iter().map(VecDeque::len).sum();
+
+		let _ = writeln!(
+			dump,
+			"overseer: {} queued messages, {} dead letters{}",
+			queued,
+			self.dropped_dead_letters,
+			if self.draining { ", draining" } else { "" },
+		);
+
+		for info in self.subsystem_infos() {
+			let id = info.id;
+			let state = if info.running {
+				"running"
+			} else if self.restarting.contains(&id) {
+				"restarting"
+			} else if self.pending_starts.contains(&id) {
+				"waiting for dependencies"
+			} else {
+				"stopped"
+			};
+			let overflow = match id {
+				SubsystemId::Validation => self.validation_subsystem.instance.as_ref().map(|s| s.overflow.len()),
+				SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance.as_ref().map(|s| s.overflow.len()),
+			};
+
+			let _ = write!(
+				dump,
+				"{:?}: {}, {} restarts, {} in mailbox, {} overflowed",
+				id,
+				state,
+				info.restarts,
+				info.mailbox_depth,
+				overflow.unwrap_or(0),
+			);
+			if self.ready.contains(&id) {
+				let _ = write!(dump, ", ready");
+			}
+			if self.paused.contains(&id) {
+				let _ = write!(dump, ", paused");
+			}
+			if self.unhealthy.contains(&id) {
+				let _ = write!(dump, ", unhealthy");
+			}
+			let _ = writeln!(dump);
+
+			for dependency in self.dependencies(id) {
+				let _ = writeln!(dump, "  depends on {:?}", dependency);
+			}
+		}
+
+		dump
+	}
+
+	/// Get the [`OverseerTopology`] of the `Overseer`, to create another one like it.
+	///
+	/// Use [`OverseerHandler::topology`] while the `Overseer` is running.
+	///
+	/// # Panics
+	///
+	/// If the `Overseer` has been polled, as it is then run elsewhere.
+	///
+	/// [`OverseerTopology`]: struct.OverseerTopology.html
+	/// [`OverseerHandler::topology`]: struct.OverseerHandler.html#method.topology
+	pub fn topology(&self) -> OverseerTopology {
+		self.assert_not_taken();
+		let running = self.running_subsystem_ids();
+		let subsystems: Vec<_> = self.boot_order.iter()
+			.filter(|id| running.contains(id) || self.restarting.contains(id) || self.pending_starts.contains(id))
+			.copied()
+			.collect();
+		let restarts = subsystems.iter()
+			.map(|id| (*id, self.starts.get(id).map_or(0, |starts| starts.saturating_sub(1))))
+			.collect();
+
+		OverseerTopology {
+			subsystems,
+			dependencies: self.config.dependencies.clone(),
+			restarts,
+		}
+	}
+
+	/// Watch the `Overseer` and call `on_stall` if it has not made any progress within `deadline`.
+	///
+	/// The `Overseer` wakes up at least twice within `deadline` even with nothing to do,
+	/// so it stalls only when it is stuck, for example waiting on a full channel.
+	/// `on_stall` is called once per stall. The watchdog runs on the executor of
+	/// the `Overseer` while it is being run, so one that is yet to be run is not stalled.
+	///
+	/// With [`OverseerConfig::stop_on_stall`] the `Overseer` also stops on the first stall,
+	/// with [`ShutdownReason::WatchdogStall`].
+	///
+	/// [`OverseerConfig::stop_on_stall`]: struct.OverseerConfig.html#structfield.stop_on_stall
+	/// [`ShutdownReason::WatchdogStall`]: enum.ShutdownReason.html#variant.WatchdogStall
+	pub fn with_watchdog(
+		mut self,
+		deadline: Duration,
+		on_stall: impl Fn() + Send + 'static,
+	) -> Self {
+		self.watchdog = Some((Arc::new(AtomicU64::new(0)), deadline));
+		self.on_stall = Some(Box::new(on_stall));
+
+		self
+	}
+
+	/// Give the `Overseer` a name to tell it apart from others in the logs.
+	///
+	/// What has been logged while the `Overseer` was being created is without it.
+	pub fn with_name(mut self, name: &'static str) -> Self {
+		self.name = Some(name);
+		self
+	}
+
+	/// Give a `Subsystem` a name for humans, which the logs show along with its `SubsystemId`.
+	pub fn with_subsystem_name(mut self, id: SubsystemId, name: &'static str) -> Self {
+		self.names.insert(id, name);
+		self
+	}
+
+	/// Pass every message through `middleware` before it is dispatched.
+	///
+	/// `middleware` is called with the sender of the message, `None` for the outside world,
+	/// its receiver and the message itself. It returns the message to dispatch, which may be
+	/// a different one, even to another receiver, or `None` to drop the message. Middleware
+	/// is called in the order it has been added in, each with what the previous one returned.
+	///
+	/// It is called on the task of the `Overseer` for every message, so all of it has to be
+	/// cheap. Anything that takes its time delays every message that follows.
+	pub fn with_middleware(
+		mut self,
+		middleware: impl Fn(Option<SubsystemId>, SubsystemId, AllMessages) -> Option<AllMessages> + Send + 'static,
+	) -> Self {
+		self.middleware.push(Box::new(middleware));
+		self
+	}
+
+	/// Deliver the recorded messages to the `Subsystem`s and stop.
+	///
+	/// Each message is delivered to its recorded receiver in the recorded order.
+	/// Messages sent by the `Subsystem`s themselves are dropped, as the ones that
+	/// were dispatched during the recording are already part of it.
+	///
+	/// Only the order of the messages is reproduced. Anything a `Subsystem` does
+	/// based on timers, randomness or the scheduling of its own tasks may still
+	/// turn out differently than during the recording.
+	#[cfg(feature = "record")]
+	pub async fn replay(mut self, records: impl IntoIterator<Item = Record>) -> SubsystemResult<()> {
+		for record in records {
+			let id = Some(record.id);
+			let delivery = match (
+				record.msg,
+				&self.validation_subsystem.instance,
+				&self.candidate_backing_subsystem.instance,
+			) {
+				(AllMessages::Validation(msg), Some(s), _) => {
+					let mut tx = s.tx.clone();
+					async move { tx.send(Envelope { id, msg: FromOverseer::Communication { msg }, span: MessageSpan::none() }).await }.boxed()
+				}
+				(AllMessages::CandidateBacking(msg), _, Some(s)) => {
+					let mut tx = s.tx.clone();
+					async move { tx.send(Envelope { id, msg: FromOverseer::Communication { msg }, span: MessageSpan::none() }).await }.boxed()
+				}
+				_ => {
+					log::warn!("{}Skipping a recorded message to {:?} which is not running", self.prefix(), self.named(record.to));
+					continue;
+				}
+			};
+			let mut delivery = delivery.fuse();
+
+			loop {
+				select! {
+					res = delivery => {
+						if let Err(e) = res {
+							log::warn!("{}Failed to replay a message to {:?} {:?}", self.prefix(), self.named(record.to), e);
+						}
+						break;
+					},
+					msg = self.running_subsystems_rx.next() => match msg {
+						Some((StreamYield::Item((_, ToOverseer::SubsystemMessage { .. })), _)) => (),
+						Some((StreamYield::Item((from, msg)), _)) => {
+							if let Err(e) = self.on_subsystem_msg(from, msg) {
+								log::warn!("{}Failed to handle what {:?} has sent while replaying {:?}", self.prefix(), self.named(from), e);
+							}
+						}
+						Some((StreamYield::Finished(f), _)) => {
+							f.remove(Pin::new(&mut self.running_subsystems_rx));
+						}
+						None => (),
+					},
+				}
+			}
+		}
+
+		self.stop().await;
+
+		Ok(())
+	}
+
+	// Stop the overseer.
+	//
+	// Every running `Subsystem` is sent a `Conclude` signal after which its
+	// channel is dropped, so even a `Subsystem` that ignores the signal will
+	// get an error from `recv()` once it has drained its queue.
+	async fn stop(&mut self) -> StopReport {
+		self.stop_within(self.config.stop_grace_period).await
+	}
+
+	// Stop the overseer, dropping the subsystems that have not concluded within `grace_period`.
+	async fn stop_within(&mut self, grace_period: Duration) -> StopReport {
+		let mut stopping: Vec<_> = self.running_subsystem_ids();
+		stopping.extend(self.restarting.drain());
+		// Nothing is started anymore, not even once its dependencies are ready.
+		self.pending_starts.clear();
+
+		if let Some(s) = self.validation_subsystem.instance.take() {
+			let _ = s.signals_tx.unbounded_send(OverseerSignal::Conclude);
+			let _ = s.cancel.send(());
+			self.emit(OverseerEvent::SubsystemStopped(SubsystemId::Validation));
+		}
+
+		if let Some(s) = self.candidate_backing_subsystem.instance.take() {
+			let _ = s.signals_tx.unbounded_send(OverseerSignal::Conclude);
+			let _ = s.cancel.send(());
+			self.emit(OverseerEvent::SubsystemStopped(SubsystemId::CandidateBacking));
+		}
+
+		let mut report = StopReport::default();
+		let mut stop_delay = self.clock.sleep(grace_period).fuse();
+
+		loop {
+			select! {
+				finished = self.running_subsystems.next() => {
+					match finished {
+						Some((id, generation, _)) if self.is_current(id, generation) => report.clean.push(id),
+						_ => (),
+					}
+
+					if self.running_subsystems.is_empty() {
+						break;
+					}
+				},
+				msg = self.running_subsystems_rx.next() => match msg {
+					Some((StreamYield::Item((from, msg)), _)) => self.on_stopping_msg(from, msg),
+					Some((StreamYield::Finished(f), _)) => {
+						f.remove(Pin::new(&mut self.running_subsystems_rx));
+					}
+					None => (),
+				},
+				_ = stop_delay => break,
+				complete => break,
+			}
+		}
+
+		report.forced = stopping.iter().copied().filter(|id| !report.clean.contains(id)).collect();
+		for id in &report.forced {
+			log::warn!("{}Subsystem {:?} has not concluded in time and is dropped", self.prefix(), self.named(*id));
+		}
+
+		for id in stopping {
+			self.on_stop(id);
+		}
+
+		report
+	}
+
+	// Conclude the subsystems, dependents before their dependencies, and stop the overseer.
+	//
+	// A subsystem is sent a `Conclude` signal once no running subsystem depends on it,
+	// and acknowledges it by finishing. The messages of the subsystems are routed
+	// meanwhile. Those that have not finished within `timeout` are dropped.
+	async fn conclude_all(&mut self, timeout: Duration) -> StopReport {
+		log::info!("{}Concluding the subsystems", self.prefix());
+
+		let mut report = StopReport::default();
+		let mut concluding = HashSet::new();
+		let mut timeout = self.clock.sleep(timeout).fuse();
+		// Nothing is started anymore, not even once its dependencies are ready.
+		self.pending_starts.clear();
+
+		loop {
+			let running = self.running_subsystem_ids();
+			if running.is_empty() {
+				break;
+			}
+
+			// If the subsystems depend on each other none of them goes first.
+			let mut next: Vec<_> = running.iter()
+				.copied()
+				.filter(|id| !running.iter().any(|dependent| self.dependencies(*dependent).any(|d| d == *id)))
+				.collect();
+			if next.is_empty() {
+				next = running;
+			}
+
+			for id in next {
+				if concluding.insert(id) {
+					log::debug!("{}Concluding subsystem {:?}", self.prefix(), self.named(id));
+					self.signal(id, OverseerSignal::Conclude);
+				}
+			}
+
+			select! {
+				finished = self.running_subsystems.next() => match finished {
+					Some((id, generation, _)) if !self.is_current(id, generation) => (),
+					Some((id, _, res)) => {
+						report.clean.push(id);
+						self.on_finished(id, res);
+						self.remove_instance(id);
+						self.emit(OverseerEvent::SubsystemStopped(id));
+					}
+					None => break,
+				},
+				msg = self.running_subsystems_rx.next() => match msg {
+					Some((StreamYield::Item((from, ToOverseer::SubsystemMessage { msg, causation_id, deadline, span, .. })), _)) => {
+						if let Err(e) = self.route_message(Some(from), causation_id, msg, deadline, span).await {
+							log::warn!("{}Failed to route a message from a concluding subsystem {:?}", self.prefix(), e);
+						}
+					}
+					Some((StreamYield::Item((from, msg)), _)) => {
+						if let Err(e) = self.on_subsystem_msg(from, msg) {
+							log::warn!("{}Failed to handle what {:?} has sent while concluding {:?}", self.prefix(), self.named(from), e);
+						}
+					}
+					Some((StreamYield::Finished(f), _)) => {
+						f.remove(Pin::new(&mut self.running_subsystems_rx));
+					}
+					None => (),
+				},
+				_ = timeout => break,
+				complete => break,
+			}
+		}
+
+		let rest = self.stop_within(Duration::from_secs(0)).await;
+		report.clean.extend(rest.clean);
+		report.forced = rest.forced;
+
+		report
+	}
+
+	// Note why the overseer is stopping along with the subsystems still running, unless it already is.
+	// A subsystem that has failed is not counted as running, even before its instance is removed.
+	fn shutting_down(&mut self, reason: ShutdownReason) {
+		if self.shutdown.is_some() {
+			return;
+		}
+
+		let mut running = self.running_subsystem_ids();
+		if let ShutdownReason::SubsystemFatal(failed, _) = &reason {
+			running.retain(|id| id != failed);
+		}
+		self.shutdown = Some(ShutdownReport { reason, running });
+	}
+
+	// Send a signal to a running subsystem.
+	fn signal(&self, id: SubsystemId, signal: OverseerSignal) {
+		let signals_tx = match id {
+			SubsystemId::Validation => self.validation_subsystem.instance.as_ref().map(|s| &s.signals_tx),
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance.as_ref().map(|s| &s.signals_tx),
+		};
+
+		if let Some(signals_tx) = signals_tx {
+			let _ = signals_tx.unbounded_send(signal);
+		}
+	}
+
+	/// Run the `Overseer` and tell why it has stopped.
+	///
+	/// This is the same as awaiting the `Overseer` itself. The [`ShutdownReport`] also lists
+	/// the `Subsystem`s that were still running at that point, for the caller to log
+	/// and decide whether to start again.
+	///
+	/// [`ShutdownReport`]: struct.ShutdownReport.html
+	pub async fn run(self) -> ShutdownReport {
+		self.await
+	}
+
+	async fn run_loop(mut self) -> ShutdownReport {
+		let (stalled_tx, stalled_rx) = oneshot::channel();
+		let watchdog = self.start_watchdog(stalled_tx);
+
+		// A watchdog that is gone without noticing a stall does not stop the overseer.
+		let stalled = Some(stalled_rx).filter(|_| self.config.stop_on_stall);
+		let mut stalled = async move {
+			let noticed = match stalled {
+				Some(stalled) => stalled.await.is_ok(),
+				None => false,
+			};
+			if !noticed {
+				future::pending().await
+			}
+		}.boxed().fuse();
+
+		loop {
+			let stopped = select! {
+				res = self.step().fuse() => res.map(|_| false),
+				() = stalled => Some(true),
+			};
+
+			match stopped {
+				Some(true) => {
+					log::error!("{}Stopping the overseer as it has stalled", self.prefix());
+					self.shutting_down(ShutdownReason::WatchdogStall);
+					self.stop().await;
+					break;
+				}
+				Some(false) => break,
+				None => (),
+			}
+		}
+
+		if let Some(watchdog) = watchdog {
+			watchdog.abort();
+		}
+
+		// Only an overseer that has run out of subsystems stops without noting why.
+		self.shutdown.take().unwrap_or(ShutdownReport {
+			reason: ShutdownReason::Clean,
+			running: Vec::new(),
+		})
+	}
+
+	// Start watching the progress of the `run` loop, if there is a watchdog, until it is aborted.
+	// The first stall is also told to `stalled_tx`.
+	fn start_watchdog(&mut self, stalled_tx: oneshot::Sender<()>) -> Option<AbortHandle> {
+		let (ticks, deadline) = match &self.watchdog {
+			Some((ticks, deadline)) => (ticks.clone(), *deadline),
+			None => return None,
+		};
+		let on_stall = self.on_stall.take()?;
+		let clock = self.clock.clone();
+		let prefix = self.prefix();
+
+		let (watch, abort) = future::abortable(async move {
+			let mut last = 0;
+			let mut stalled = false;
+			let mut stalled_tx = Some(stalled_tx);
+
+			loop {
+				clock.sleep(deadline).await;
+
+				let now = ticks.load(Ordering::Relaxed);
+				if now == last {
+					if !stalled {
+						log::error!("{}Overseer has not made progress in {:?}", prefix, deadline);
+						on_stall();
+
+						if let Some(stalled_tx) = stalled_tx.take() {
+							let _ = stalled_tx.send(());
+						}
+					}
+					stalled = true;
+				} else {
+					stalled = false;
+				}
+
+				last = now;
+			}
+		});
+
+		if let Err(e) = self.s.spawn(watch.map(drop)) {
+			log::error!("{}Failed to start the watchdog {:?}", self.prefix(), e);
+			return None;
+		}
+
+		self.watchdog_ticks.push(self.clock.sleep(deadline / 2));
+
+		Some(abort)
+	}
+
+	/// Handle the next thing that happens to the `Overseer`, be it a message or an exited `Subsystem`.
+	///
+	/// This is one iteration of [`run`], so a test can drive the `Overseer` a step at a time
+	/// and check its state in between. Once the `Overseer` has finished this returns whether
+	/// it has failed, and it must not be stepped any further.
+	///
+	/// [`run`]: #method.run
+	pub async fn step(&mut self) -> Option<SubsystemResult<()>> {
+		if let Some((ticks, _)) = &self.watchdog {
+			ticks.fetch_add(1, Ordering::Relaxed);
+		}
+
+		loop {
+			// The overflow of a paused subsystem is kept until it is resumed,
+			// and the one of a subsystem being replaced is for its replacement
+			// once the conclusion has been sent.
+			let validation_held = self.is_overflow_held(SubsystemId::Validation);
+			let candidate_backing_held = self.is_overflow_held(SubsystemId::CandidateBacking);
+
+			// Over the budget nothing more is taken from the subsystems, nor from the outside
+			// world once a message of theirs is held, until the mailboxes have drained.
+			let mut collected = if self.is_over_budget() {
+				Either::Right(self.drained().map(|()| None).fuse())
+			} else {
+				Either::Left(self.running_subsystems_rx.next())
+			};
+			let mut validation = match &mut self.validation_subsystem.instance {
+				Some(s) if !s.overflow.is_empty() && !validation_held => Some(s),
+				_ => None,
+			};
+			let mut candidate_backing = match &mut self.candidate_backing_subsystem.instance {
+				Some(s) if !s.overflow.is_empty() && !candidate_backing_held => Some(s),
+				_ => None,
+			};
+			let per_pass = self.config.messages_per_pass.max(1);
+			let mut overflow = if validation.is_some() || candidate_backing.is_some() {
+				future::poll_fn(move |cx| {
+					let validation = validation.as_mut().map_or(Poll::Pending, |s| s.poll_overflow(cx, per_pass));
+					let candidate_backing = candidate_backing.as_mut().map_or(Poll::Pending, |s| s.poll_overflow(cx, per_pass));
+
+					if validation.is_ready() || candidate_backing.is_ready() {
+						Poll::Ready(())
+					} else {
+						Poll::Pending
+					}
+				}).fuse()
+			} else {
+				future::Fuse::terminated()
+			};
+
+			let mut events = match self.held_external {
+				Some(ref mut held) => Either::Right(held.map(|msg| Some(Event::MsgToSubsystem(msg))).fuse()),
+				None => Either::Left(self.events_rx.next()),
+			};
+
+			select! {
+				msg = events => match msg {
+					Some(Event::MsgToSubsystem(msg)) if self.is_over_budget() => {
+						log::debug!("{}Holding a message from the outside world until the mailboxes have drained", self.prefix());
+						self.held_external = Some(self.drained().map(move |()| msg).boxed());
+					}
+					Some(Event::MsgToSubsystem(msg)) => {
+						self.held_external = None;
+						if let Err(e) = self.route_message(None, None, msg, None, MessageSpan::none()).await {
+							log::warn!("{}Failed to route a message from the outside world {:?}", self.prefix(), e);
+						}
+					}
+					Some(Event::ListSubsystems(tx)) => {
+						let _ = tx.send(self.running_subsystem_ids());
+					}
+					Some(Event::Subscribe(tx)) => self.event_subscribers.push(tx),
+					Some(Event::Health(tx)) => {
+						let _ = tx.send(self.health());
+					}
+					Some(Event::Inspect(tx)) => {
+						let _ = tx.send(self.subsystem_infos());
+					}
+					#[cfg(feature = "timing")]
+					Some(Event::Timings(tx)) => {
+						let _ = tx.send(self.timings.clone());
+					}
+					Some(Event::DebugDump(tx)) => {
+						let _ = tx.send(self.debug_dump());
+					}
+					Some(Event::Topology(tx)) => {
+						let _ = tx.send(self.topology());
+					}
+					Some(Event::RecentDispatches(tx)) => {
+						let _ = tx.send(self.dispatch_log.iter().cloned().collect());
+					}
+					Some(Event::Pause(id)) => {
+						log::info!("{}Pausing the delivery of messages to subsystem {:?}", self.prefix(), self.named(id));
+						self.paused.insert(id);
+					}
+					Some(Event::Resume(id)) => {
+						log::info!("{}Resuming the delivery of messages to subsystem {:?}", self.prefix(), self.named(id));
+						self.paused.remove(&id);
+					}
+					Some(Event::AwaitSubsystem(id, tx)) => {
+						let running = self.running_subsystem_ids().contains(&id)
+							|| self.pending_starts.contains(&id);
+
+						match self.results.get(&id) {
+							Some(res) if !running => {
+								let _ = tx.send(res.clone());
+							}
+							None if !running => {
+								let _ = tx.send(Err(SubsystemError::UnknownSubsystem(id)));
+							}
+							_ => self.completion_waiters.push((id, tx)),
+						}
+					}
+					Some(Event::AwaitReady(id, tx)) => {
+						if self.ready.contains(&id) {
+							let _ = tx.send(());
+						} else {
+							self.ready_waiters.push((id, tx));
+						}
+					}
+					Some(Event::Drain) => {
+						self.drain();
+
+						if self.running_subsystems.is_empty() {
+							return Some(Ok(()));
+						}
+					}
+					Some(Event::Stop) => {
+						self.shutting_down(ShutdownReason::ExternalStop);
+						self.stop().await;
+						return Some(Ok(()));
+					}
+					Some(Event::StopWithTimeout(timeout, tx)) => {
+						self.shutting_down(ShutdownReason::ExternalStop);
+						let report = self.stop_within(timeout).await;
+						let _ = tx.send(report);
+						return Some(Ok(()));
+					}
+					Some(Event::ConcludeAll(timeout, tx)) => {
+						self.shutting_down(ShutdownReason::ExternalStop);
+						let report = self.conclude_all(timeout).await;
+						let _ = tx.send(report);
+						return Some(Ok(()));
+					}
+					Some(Event::SpawnSubsystem(s, tx)) => {
+						let _ = tx.send(self.spawn_subsystem(s));
+					}
+					Some(Event::ReplaceSubsystem(s, tx)) => self.replace_subsystem(s, tx),
+					Some(_) => (),
+					// All handlers are gone, the subsystems may still be working.
+					None => continue,
+				},
+				msg = collected => {
+					// Take what else the subsystems have sent by now, so that the messages
+					// are dispatched in the order of their priority. The subsystems are
+					// polled in turns, so the pass ends once any of them has sent
+					// `messages_per_pass` times its weight messages.
+					let mut msg = msg;
+					self.taken.clear();
+					for _ in 0..MAX_MESSAGES_PER_PASS {
+						match msg {
+							Some((StreamYield::Item((from, msg)), _)) => {
+								#[cfg(feature = "timing")]
+								let started = self.clock.now();
+
+								if let Err(e) = self.on_subsystem_msg(from, msg) {
+									log::error!("{}Failed to start the dependents of {:?} {:?}", self.prefix(), self.named(from), e);
+									self.shutting_down(ShutdownReason::SubsystemFatal(from, e.clone()));
+									self.stop().await;
+									return Some(Err(e));
+								}
+
+								#[cfg(feature = "timing")]
+								{
+									*self.timings.collection.entry(from).or_default() += self.clock.now() - started;
+								}
+
+								let share = (self.config.messages_per_pass * self.config.weight(from) as usize).max(1);
+								let taken = self.taken.entry(from).or_insert(0);
+								*taken += 1;
+								if *taken >= share {
+									break;
+								}
+							}
+							Some((StreamYield::Finished(f), _)) => {
+								f.remove(Pin::new(&mut self.running_subsystems_rx));
+							}
+							None => break,
+						}
+
+						msg = match self.running_subsystems_rx.next().now_or_never() {
+							Some(msg) => msg,
+							None => break,
+						};
+					}
+
+					self.dispatch_queued().await;
+				},
+				id = self.throttle_ticks.next() => {
+					let id = match id {
+						Some(id) => id,
+						None => continue,
+					};
+
+					self.release_throttled(id);
+					self.dispatch_queued().await;
+				},
+				// Some subsystem exited? Unless it has concluded apply the exit policy.
+				finished = self.running_subsystems.next() => match finished {
+					// A subsystem that has been restarted meanwhile has a newer handle.
+					Some((id, generation, _)) if !self.is_current(id, generation) => {
+						log::debug!("{}Ignoring a stale handle of subsystem {:?} from before a restart", self.prefix(), self.named(id));
+					}
+					Some((id, _, res)) if self.replacing.contains_key(&id) => {
+						self.on_finished(id, res);
+						self.emit(OverseerEvent::SubsystemStopped(id));
+
+						let (replacement, tx) = self.replacing.remove(&id).expect("checked above; qed");
+						let res = self.start_replacement(replacement);
+						let _ = tx.send(res.clone().map(|_| id));
+
+						if let Err(e) = res {
+							log::error!("{}Failed to start the replacement of subsystem {:?} {:?}", self.prefix(), self.named(id), e);
+							self.shutting_down(ShutdownReason::SubsystemFatal(id, e.clone()));
+							self.stop().await;
+							return Some(Err(e));
+						}
+					}
+					Some((id, _, res)) if self.restarting.contains(&id) => {
+						self.restarting.remove(&id);
+						self.on_finished(id, res);
+						self.emit(OverseerEvent::SubsystemStopped(id));
+
+						if self.draining {
+							if self.running_subsystems.is_empty() {
+								return Some(Ok(()));
+							}
+
+							return None;
+						}
+
+						if let Err(e) = self.start_subsystem(id) {
+							log::error!("{}Failed to restart subsystem {:?} {:?}", self.prefix(), self.named(id), e);
+							self.shutting_down(ShutdownReason::SubsystemFatal(id, e.clone()));
+							self.stop().await;
+							return Some(Err(e));
+						}
+					}
+					Some((id, _, Ok(()))) => {
+						let concluded = self.has_concluded(id);
+						// One that has dropped its context has been stopped already.
+						let running = self.running_subsystem_ids().contains(&id);
+						self.on_finished(id, Ok(()));
+						self.remove_instance(id);
+
+						if concluded {
+							log::debug!("{}Subsystem {:?} has finished after concluding", self.prefix(), self.named(id));
+							self.emit(OverseerEvent::SubsystemStopped(id));
+						} else if running {
+							log::warn!("{}Subsystem {:?} has exited without concluding", self.prefix(), self.named(id));
+							self.emit(OverseerEvent::SubsystemExited(id));
+						}
+
+						if self.draining && self.running_subsystems.is_empty() {
+							return Some(Ok(()));
+						}
+					}
+					Some((id, _, Err(e))) => {
+						log::error!("{}Subsystem {:?} finished unexpectedly {:?}", self.prefix(), self.named(id), e);
+						self.on_finished(id, Err(e.clone()));
+						self.emit(OverseerEvent::SubsystemFailed(id));
+
+						// Nothing is restarted while draining.
+						if self.draining {
+							self.remove_instance(id);
+
+							if self.running_subsystems.is_empty() {
+								return Some(Ok(()));
+							}
+
+							return None;
+						}
+
+						if self.schedule_restart(id) {
+							self.restart_siblings(id);
+							return None;
+						}
+
+						match self.config.exit_policy {
+							ExitPolicy::Panic => {
+								self.shutting_down(ShutdownReason::SubsystemFatal(id, e));
+								self.stop().await;
+								return Some(Err(SubsystemError::SubsystemStopped(id)));
+							}
+							ExitPolicy::Restart => {
+								self.restart_siblings(id);
+
+								if let Err(e) = self.start_subsystem(id) {
+									log::error!("{}Failed to restart subsystem {:?} {:?}", self.prefix(), self.named(id), e);
+									self.shutting_down(ShutdownReason::SubsystemFatal(id, e.clone()));
+									self.stop().await;
+									return Some(Err(e));
+								}
+							}
+							ExitPolicy::Ignore => self.remove_instance(id),
+						}
+					}
+					None => continue,
+				},
+				id = self.pending_restarts.next() => {
+					let id = match id {
+						Some(id) => id,
+						None => continue,
+					};
+
+					// It may have been spawned through the handler meanwhile.
+					if self.draining || self.running_subsystem_ids().contains(&id) {
+						return None;
+					}
+
+					if let Err(e) = self.start_subsystem(id) {
+						log::error!("{}Failed to restart subsystem {:?} {:?}", self.prefix(), self.named(id), e);
+						self.shutting_down(ShutdownReason::SubsystemFatal(id, e.clone()));
+						self.stop().await;
+						return Some(Err(e));
+					}
+				},
+				id = self.start_timeouts.next() => {
+					let id = match id {
+						Some(id) => id,
+						None => continue,
+					};
+
+					if self.pending_starts.contains(&id) {
+						log::error!("{}Dependencies of subsystem {:?} are not ready in time", self.prefix(), self.named(id));
+						self.shutting_down(ShutdownReason::SubsystemFatal(id, SubsystemError::DependencyTimeout));
+						self.stop().await;
+						return Some(Err(SubsystemError::DependencyTimeout));
+					}
+				},
+				check = self.health_check.next() => {
+					let timeout = match (check, self.config.liveness_timeout) {
+						(Some(()), Some(timeout)) => timeout,
+						_ => continue,
+					};
+
+					self.check_health(timeout);
+					self.health_check.push(self.clock.sleep(timeout));
+				},
+				// Some channels have made room for the messages that have overflowed them.
+				_ = overflow => (),
+				tick = self.watchdog_ticks.next() => {
+					let deadline = match (tick, &self.watchdog) {
+						(Some(()), Some((_, deadline))) => *deadline,
+						_ => continue,
+					};
+
+					self.watchdog_ticks.push(self.clock.sleep(deadline / 2));
+				},
+				deadline = self.drain_deadline.next() => {
+					if deadline.is_none() {
+						continue;
+					}
+
+					log::warn!("{}The subsystems have not drained in time, stopping them", self.prefix());
+					self.shutting_down(ShutdownReason::ExternalStop);
+					self.stop().await;
+					return Some(Ok(()));
+				},
+				complete => return Some(Ok(())),
+			}
+
+			if self.config.stop_when_empty && self.is_empty() {
+				log::info!("{}No subsystems left, stopping", self.prefix());
+				return Some(Ok(()));
+			}
+
+			return None;
+		}
+	}
+
+	// Whether no subsystem is running, about to be started or restarted.
+	fn is_empty(&self) -> bool {
+		self.running_subsystems.is_empty()
+			&& self.pending_starts.is_empty()
+			&& self.pending_restarts.is_empty()
+	}
+
+	async fn route_message(
+		&mut self,
+		from: Option<SubsystemId>,
+		causation_id: Option<MessageId>,
+		mut msg: AllMessages,
+		deadline: Option<Instant>,
+		span: MessageSpan,
+	) -> SubsystemResult<()> {
+		for middleware in &self.middleware {
+			msg = match middleware(from, msg.recipient(), msg) {
+				Some(msg) => msg,
+				None => {
+					log::trace!("{}Middleware has dropped a message from {:?}", self.prefix(), from.map(|from| self.named(from)));
+					return Ok(());
+				}
+			};
+		}
+
+		let to = msg.recipient();
+
+		if self.config.log_payloads {
+			log::trace!("{}Dispatching a message from={:?} to={:?} kind={} msg={:?}", self.prefix(), from.map(|from| self.named(from)), self.named(to), msg.kind(), msg);
+		} else {
+			log::trace!("{}Dispatching a message from={:?} to={:?} kind={}", self.prefix(), from.map(|from| self.named(from)), self.named(to), msg.kind());
+		}
+
+		if let Some(from) = from {
+			self.metrics.subsystem(from).sent.fetch_add(1, Ordering::Relaxed);
+		}
+
+		if matches!(deadline, Some(deadline) if deadline <= self.clock.now()) {
+			log::debug!("{}Dropping a message from {:?} to {:?} past its deadline", self.prefix(), from.map(|from| self.named(from)), self.named(to));
+			self.emit(OverseerEvent::MessageExpired { from, to });
+			return Ok(());
+		}
+
+		// Unless it is being replaced, a subsystem that has concluded is not going to receive it.
+		if self.has_concluded(to) && !self.replacing.contains_key(&to) {
+			log::warn!("{}Dropping a message from {:?} to {:?} which has concluded", self.prefix(), from.map(|from| self.named(from)), self.named(to));
+			self.undeliverable(from, to, msg);
+			return Ok(());
+		}
+
+		let id = MessageId(self.next_message_id);
+		let kind = msg.kind();
+
+		#[cfg(feature = "record")]
+		let recorded = self.config.recorder.as_ref().map(|_| msg.without_responder());
+
+		let overflow_capacity = self.config.overflow_capacity;
+		let retries = self.config.delivery_retries;
+		let policy = self.config.mailbox_policy;
+		let paused = self.is_held(to);
+		#[cfg(feature = "timing")]
+		let started = self.clock.now();
+		let delivery = match (
+			msg,
+			&mut self.validation_subsystem.instance,
+			&mut self.candidate_backing_subsystem.instance,
+		) {
+			(AllMessages::Validation(msg), Some(s), _) => {
+				let envelope = Envelope { id: Some(id), msg: FromOverseer::Communication { msg }, span };
+				s.deliver(envelope, overflow_capacity, retries, policy, paused).await
+					.map(|delivery| delivery.map(AllMessages::Validation))
+			}
+			(AllMessages::CandidateBacking(msg), _, Some(s)) => {
+				let envelope = Envelope { id: Some(id), msg: FromOverseer::Communication { msg }, span };
+				s.deliver(envelope, overflow_capacity, retries, policy, paused).await
+					.map(|delivery| delivery.map(AllMessages::CandidateBacking))
+			}
+			(msg, _, _) => {
+				log::warn!("{}Dropping a message from {:?} to {:?} which is not running", self.prefix(), from.map(|from| self.named(from)), self.named(to));
+				self.undeliverable(from, to, msg);
+				return Ok(());
+			}
+		};
+
+		#[cfg(feature = "timing")]
+		{
+			*self.timings.dispatch.entry(to).or_default() += self.clock.now() - started;
+		}
+
+		let delivery = match delivery {
+			Ok(delivery) => delivery,
+			Err(SubsystemError::ChannelClosed) => {
+				self.on_mailbox_closed(to);
+				return Err(SubsystemError::ChannelClosed);
+			}
+			Err(e) => return Err(e),
+		};
+
+		match delivery {
+			Delivery::Sent => (),
+			Delivery::Overflowing => {
+				log::warn!("{}Waiting for room in the channel to {:?} with its overflow full", self.prefix(), self.named(to));
+				self.emit(OverseerEvent::MailboxOverflow(to));
+
+				#[cfg(feature = "timing")]
+				let started = self.clock.now();
+				let waited = match to {
+					SubsystemId::Validation => match self.validation_subsystem.instance {
+						Some(ref mut s) => s.make_room(overflow_capacity).await,
+						None => Ok(()),
+					},
+					SubsystemId::CandidateBacking => match self.candidate_backing_subsystem.instance {
+						Some(ref mut s) => s.make_room(overflow_capacity).await,
+						None => Ok(()),
+					},
+				};
+				#[cfg(feature = "timing")]
+				{
+					*self.timings.dispatch.entry(to).or_default() += self.clock.now() - started;
+				}
+
+				match waited {
+					Ok(()) => (),
+					Err(SubsystemError::ChannelClosed) => {
+						self.on_mailbox_closed(to);
+						return Err(SubsystemError::ChannelClosed);
+					}
+					Err(e) => return Err(e),
+				}
+			}
+			Delivery::Full(msg) => {
+				log::warn!("{}Dropping a message from {:?} to {:?} whose mailbox is full", self.prefix(), from.map(|from| self.named(from)), self.named(to));
+				self.emit(OverseerEvent::MailboxOverflow(to));
+				self.undeliverable(from, to, msg);
+				return Ok(());
+			}
+		}
+
+		#[cfg(feature = "record")]
+		{
+			if let (Some(recorder), Some(msg)) = (&self.config.recorder, recorded) {
+				let _ = recorder.unbounded_send(Record { id, from, to, msg });
+			}
+		}
+
+		self.next_message_id += 1;
+		self.last_dispatch.insert(to, self.clock.now());
+		self.metrics.subsystem(to).dispatched.fetch_add(1, Ordering::Relaxed);
+		self.log_dispatch(from, to, kind);
+		self.emit(OverseerEvent::MessageDispatched { id, causation_id, from, to });
+		self.check_lagging(to);
+
+		Ok(())
+	}
+
+	// Dispatch the messages taken from the subsystems so far.
+	async fn dispatch_queued(&mut self) {
+		self.order_queued_by_weight();
+		while let Some(queued) = self.next_queued() {
+			if let Err(e) = self.route_message(Some(queued.from), queued.causation_id, queued.msg, queued.deadline, queued.span).await {
+				log::warn!("{}Failed to route a message from a subsystem {:?}", self.prefix(), e);
+			}
+		}
+	}
+
+	// Take a token for a message from a subsystem, `false` if it is over its rate limit.
+	fn take_token(&mut self, id: SubsystemId) -> bool {
+		let limit = match self.config.rate_limits.get(&id) {
+			Some(limit) => *limit,
+			None => return true,
+		};
+
+		let now = self.clock.now();
+		let burst = f64::from(limit.burst.max(1));
+		let (tokens, refilled) = self.rate_buckets.entry(id).or_insert((burst, now));
+		let elapsed = now.duration_since(*refilled).as_secs_f64();
+		*tokens = (*tokens + elapsed * f64::from(limit.messages_per_second.max(1))).min(burst);
+		*refilled = now;
+
+		if *tokens >= 1.0 {
+			*tokens -= 1.0;
+			true
+		} else {
+			false
+		}
+	}
+
+	// Keep or drop a message from a subsystem over its rate limit, as its policy says.
+	fn throttle(&mut self, priority: Priority, queued: QueuedMessage) {
+		let from = queued.from;
+		let policy = self.config.rate_limits.get(&from).map(|limit| limit.policy);
+		let kept = self.throttled.entry(from).or_default();
+
+		if policy == Some(RateLimitPolicy::Drop) || kept.len() >= self.config.overflow_capacity {
+			log::warn!("{}Dropping a message from {:?} over its rate limit", self.prefix(), self.named(from));
+			self.emit(OverseerEvent::RateLimited(from));
+			return;
+		}
+
+		kept.push_back((priority, queued));
+		if kept.len() == 1 {
+			self.schedule_throttle_tick(from);
+		}
+	}
+
+	// Queue the messages kept for a subsystem that its rate limit allows for by now.
+	fn release_throttled(&mut self, id: SubsystemId) {
+		while matches!(self.throttled.get(&id), Some(kept) if !kept.is_empty()) {
+			if !self.take_token(id) {
+				self.schedule_throttle_tick(id);
+				return;
+			}
+
+			if let Some((priority, queued)) = self.throttled.get_mut(&id).and_then(VecDeque::pop_front) {
+				self.queued[priority as usize].push_back(queued);
+			}
+		}
+	}
+
+	// Wake up once the subsystem has a token again.
+	fn schedule_throttle_tick(&mut self, id: SubsystemId) {
+		let (tokens, rate) = match (self.rate_buckets.get(&id), self.config.rate_limits.get(&id)) {
+			(Some((tokens, _)), Some(limit)) => (*tokens, f64::from(limit.messages_per_second.max(1))),
+			_ => return,
+		};
+
+		let wait = Duration::from_secs_f64((1.0 - tokens).max(0.0) / rate);
+		self.throttle_ticks.push(self.clock.sleep(wait).map(move |_| id).boxed());
+	}
+
+	// Warn once the mailbox of a subsystem has grown to its high water mark,
+	// and again only after it has shrunk below the mark.
+	fn check_lagging(&mut self, id: SubsystemId) {
+		let high_water_mark = match id {
+			SubsystemId::Validation => self.validation_subsystem.channels.high_water_mark,
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.channels.high_water_mark,
+		};
+		let high_water_mark = match high_water_mark {
+			Some(mark) => mark as u64,
+			None => return,
+		};
+
+		let depth = self.metrics.subsystem(id).snapshot().mailbox_depth;
+		if depth < high_water_mark {
+			self.lagging.remove(&id);
+		} else if self.lagging.insert(id) {
+			log::warn!("{}Subsystem {:?} is lagging behind with {} messages in its mailbox", self.prefix(), self.named(id), depth);
+			self.metrics.subsystem(id).lagging.fetch_add(1, Ordering::Relaxed);
+			self.emit(OverseerEvent::SubsystemLagging(id, depth));
+		}
+	}
+
+	// Keep an entry about a dispatched message, dropping the oldest one once there are too many.
+	fn log_dispatch(&mut self, from: Option<SubsystemId>, to: SubsystemId, kind: &'static str) {
+		let capacity = self.config.dispatch_log_capacity;
+		if capacity == 0 {
+			return;
+		}
+
+		if self.dispatch_log.len() == capacity {
+			self.dispatch_log.pop_front();
+		}
+		self.dispatch_log.push_back(DispatchEntry { from, to, kind, at: self.clock.now() });
+	}
+
+	fn undeliverable(&mut self, from: Option<SubsystemId>, to: SubsystemId, msg: AllMessages) {
+		self.metrics.undeliverable.fetch_add(1, Ordering::Relaxed);
+		self.emit(OverseerEvent::MessageUndeliverable { from, to });
+
+		if let Some(ref mut dead_letters) = self.config.dead_letters {
+			if dead_letters.try_send((to, msg)).is_err() {
+				self.dropped_dead_letters += 1;
+				log::warn!("{}Dropped {} dead letters in total", self.prefix(), self.dropped_dead_letters);
+			}
+		}
+	}
+
+	// Record the result of a finished subsystem and pass it to those awaiting it,
+	// once the subsystem has released what it has acquired for it.
+	fn on_finished(&mut self, id: SubsystemId, res: SubsystemResult<()>) {
+		self.on_stop(id);
+
+		let (notified, waiting): (Vec<_>, _) = self.completion_waiters
+			.drain(..)
+			.partition(|(waiting_for, _)| *waiting_for == id);
+		self.completion_waiters = waiting;
+
+		for (_, tx) in notified {
+			let _ = tx.send(res.clone());
+		}
+
+		self.results.insert(id, res);
+	}
+
+	// Let a subsystem release what it has acquired for an instance that has finished or been dropped.
+	fn on_stop(&mut self, id: SubsystemId) {
+		log::debug!("{}Calling on_stop of subsystem {:?}", self.prefix(), self.named(id));
+
+		match id {
+			SubsystemId::Validation => self.validation_subsystem.subsystem.on_stop(),
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.subsystem.on_stop(),
+		}
+	}
+
+	// Handle something a subsystem has sent once the overseer is stopping. Its messages are
+	// undeliverable, as the channels to the subsystems have all been dropped by then.
+	fn on_stopping_msg(&mut self, from: SubsystemId, msg: ToOverseer) {
+		match msg {
+			ToOverseer::SubsystemMessage { msg, .. } => {
+				let to = msg.recipient();
+				log::debug!("{}Dropping a message from {:?} to {:?} while stopping", self.prefix(), self.named(from), self.named(to));
+				self.undeliverable(Some(from), to, msg);
+			}
+			msg => {
+				if let Err(e) = self.on_subsystem_msg(from, msg) {
+					log::warn!("{}Failed to handle what {:?} has sent while stopping {:?}", self.prefix(), self.named(from), e);
+				}
+			}
+		}
+	}
+
+	// Handle something a subsystem has sent, its messages are queued to be dispatched.
+	fn on_subsystem_msg(&mut self, from: SubsystemId, msg: ToOverseer) -> SubsystemResult<()> {
+		match msg {
+			ToOverseer::SubsystemMessage { msg, causation_id, priority, deadline, span } => {
+				let queued = QueuedMessage {
+					from,
+					causation_id,
+					msg,
+					deadline,
+					span,
+				};
+
+				// Messages already kept for being over the limit go first.
+				let throttled = matches!(self.throttled.get(&from), Some(kept) if !kept.is_empty());
+				if throttled || !self.take_token(from) {
+					self.throttle(priority, queued);
+				} else {
+					self.queued[priority as usize].push_back(queued);
+				}
+			}
+			ToOverseer::SpawnJob { s, res } => {
+				let s = self.spawn_job(s);
+
+				let _ = res.send(s);
+			}
+			ToOverseer::Heartbeat => {
+				self.heartbeats.insert(from, self.clock.now());
+				self.unhealthy.remove(&from);
+			}
+			ToOverseer::Ready => self.on_ready(from)?,
+			ToOverseer::Peers(tx) => {
+				let mut peers = self.running_subsystem_ids();
+				peers.retain(|id| *id != from);
+
+				let _ = tx.send(peers);
+			}
+			ToOverseer::Concluded => {
+				log::info!("{}Subsystem {:?} concluded", self.prefix(), self.named(from));
+			}
+		}
+
+		Ok(())
+	}
+
+	// Order the queued messages of each priority to the heavier recipient first,
+	// then from the heavier sender first.
+	//
+	// The order is stable, so the messages from one subsystem to another keep theirs.
+	fn order_queued_by_weight(&mut self) {
+		if self.config.weights.is_empty() {
+			return;
+		}
+
+		let config = &self.config;
+		for queued in self.queued.iter_mut() {
+			queued.make_contiguous().sort_by_key(|queued| {
+				std::cmp::Reverse((config.weight(queued.msg.recipient()), config.weight(queued.from)))
+			});
+		}
+	}
+
+	// Take the next queued message.
+	//
+	// This is the one of the highest priority, unless a message of a lower priority
+	// has been passed over `MAX_PRIORITY_STREAK` times, then it goes first.
+	fn next_queued(&mut self) -> Option<QueuedMessage> {
+		let queued = &self.queued;
+		let waiting = |priority: &usize| !queued[*priority].is_empty();
+
+		let priority = (0..queued.len())
+			.rev()
+			.filter(waiting)
+			.find(|priority| self.passed_over[*priority] >= MAX_PRIORITY_STREAK)
+			.or_else(|| (0..queued.len()).find(waiting))?;
+
+		for other in (0..queued.len()).filter(waiting) {
+			self.passed_over[other] += 1;
+		}
+		self.passed_over[priority] = 0;
+
+		self.queued[priority].pop_front()
+	}
+
+	// Mark the subsystems that have not sent a heartbeat within `timeout` as unhealthy.
+	fn check_health(&mut self, timeout: Duration) {
+		let now = self.clock.now();
+		let missed: Vec<_> = self.heartbeats.iter()
+			.filter(|(id, last)| now.duration_since(**last) > timeout && !self.unhealthy.contains(*id))
+			.map(|(id, _)| *id)
+			.collect();
+
+		for id in missed {
+			log::warn!("{}Subsystem {:?} has not sent a heartbeat for {:?}", self.prefix(), self.named(id), timeout);
+			self.unhealthy.insert(id);
+			self.emit(OverseerEvent::SubsystemUnhealthy(id));
+
+			if self.config.restart_unhealthy {
+				self.abort_instance(id);
+			}
+		}
+	}
+
+	fn health(&self) -> HashMap<SubsystemId, Health> {
+		self.running_subsystem_ids()
+			.into_iter()
+			.map(|id| {
+				let health = if self.unhealthy.contains(&id) {
+					Health::Unhealthy
+				} else {
+					Health::Healthy
+				};
+
+				(id, health)
+			})
+			.collect()
+	}
+
+	// Stop the subsystems to be restarted along with a failed one, as the `SupervisionStrategy` says.
+	fn restart_siblings(&mut self, id: SubsystemId) {
+		let position = self.boot_order.iter().position(|started| *started == id);
+		let siblings: Vec<_> = match (self.config.supervision, position) {
+			(SupervisionStrategy::OneForAll, _) => self.boot_order.iter()
+				.filter(|sibling| **sibling != id)
+				.copied()
+				.collect(),
+			(SupervisionStrategy::RestForOne, Some(position)) => self.boot_order[position + 1..].to_vec(),
+			_ => return,
+		};
+
+		for sibling in siblings {
+			if self.running_subsystem_ids().contains(&sibling) {
+				log::info!("{}Restarting subsystem {:?} along with {:?}", self.prefix(), self.named(sibling), self.named(id));
+				self.remove_instance(sibling);
+				self.restarting.insert(sibling);
+			}
+		}
+	}
+
+	fn subsystem_infos(&self) -> Vec<SubsystemInfo> {
+		let running = self.running_subsystem_ids();
+
+		SubsystemId::ALL.iter()
+			.map(|id| SubsystemInfo {
+				id: *id,
+				running: running.contains(id),
+				mailbox_depth: self.metrics.subsystem(*id).snapshot().mailbox_depth,
+				restarts: self.starts.get(id).map_or(0, |starts| starts.saturating_sub(1)),
+				since_last_dispatch: self.last_dispatch.get(id).map(|at| self.clock.now().saturating_duration_since(*at)),
+			})
+			.collect()
+	}
+
+	// A subsystem that has dropped its context without concluding is not sent anything anymore.
+	// It is no longer running, but its future is left to finish, and what it finishes with
+	// is handled as for any other subsystem.
+	fn on_mailbox_closed(&mut self, id: SubsystemId) {
+		if self.has_concluded(id) || !self.running_subsystem_ids().contains(&id) {
+			return;
+		}
+
+		log::warn!("{}Subsystem {:?} has dropped its context without concluding", self.prefix(), self.named(id));
+		self.remove_instance(id);
+		self.emit(OverseerEvent::SubsystemStopped(id));
+	}
+
+	fn abort_instance(&mut self, id: SubsystemId) {
+		let abort = match id {
+			SubsystemId::Validation => self.validation_subsystem.instance.as_ref().map(|s| &s.abort),
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance.as_ref().map(|s| &s.abort),
+		};
+
+		if let Some(abort) = abort {
+			abort.abort();
+		}
+	}
+
+	// Record that a subsystem is ready and start the subsystems that were waiting for it.
+	fn on_ready(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		log::debug!("{}Subsystem {:?} is ready", self.prefix(), self.named(id));
+		self.ready.insert(id);
+
+		let (notified, waiting): (Vec<_>, _) = self.ready_waiters
+			.drain(..)
+			.partition(|(waiting_for, _)| *waiting_for == id);
+		self.ready_waiters = waiting;
+
+		for (_, tx) in notified {
+			let _ = tx.send(());
+		}
+
+		let startable: Vec<_> = self.pending_starts.iter()
+			.copied()
+			.filter(|id| self.dependencies(*id).all(|dependency| self.ready.contains(&dependency)))
+			.collect();
+		self.pending_starts.retain(|id| !startable.contains(id));
+
+		for id in startable {
+			self.start_subsystem(id)?;
+		}
+
+		Ok(())
+	}
+
+	fn dependencies(&self, id: SubsystemId) -> impl Iterator<Item = SubsystemId> + '_ {
+		self.config.dependencies.iter()
+			.filter(move |(dependent, _)| *dependent == id)
+			.map(|(_, dependency)| *dependency)
+	}
+
+	// Start draining the subsystems.
+	//
+	// The `Conclude` signal is queued behind the pending messages so that
+	// every subsystem processes them first. Those that have not concluded
+	// within the `drain_timeout` are stopped.
+	fn drain(&mut self) {
+		if self.draining {
+			return;
+		}
+
+		log::info!("{}Draining the subsystems", self.prefix());
+		self.draining = true;
+		self.drain_deadline.push(self.clock.sleep(self.config.drain_timeout));
+
+		if let Some(ref mut s) = self.validation_subsystem.instance {
+			s.queue_signal(OverseerSignal::Conclude);
+		}
+
+		if let Some(ref mut s) = self.candidate_backing_subsystem.instance {
+			s.queue_signal(OverseerSignal::Conclude);
+		}
+	}
+
+	// Send an event to every subscriber that keeps up with them.
+	fn emit(&mut self, event: OverseerEvent) {
+		self.event_subscribers.retain(|tx| !tx.is_closed());
+
+		let prefix = self.prefix();
+		for tx in self.event_subscribers.iter_mut() {
+			if tx.try_send(event.clone()).is_err() {
+				log::debug!("{}Dropping event {:?} for a slow subscriber", prefix, event);
+			}
+		}
+	}
+
+	// Schedule a restart of a failed subsystem according to the `restart_policy`.
+	//
+	// Returns `false` if the subsystem should not be restarted.
+	fn schedule_restart(&mut self, id: SubsystemId) -> bool {
+		let policy = match self.config.restart_policy {
+			Some(ref policy) => policy,
+			None => return false,
+		};
+
+		let attempts = self.restarts.entry(id).or_insert(0);
+		if *attempts >= policy.max_retries {
+			log::error!("{}Subsystem {:?} exceeded {} restarts", self.prefix(), self.named(id), policy.max_retries);
+			return false;
+		}
+
+		let delay = policy.delay(*attempts);
+		*attempts += 1;
+
+		self.remove_instance(id);
+		self.pending_restarts.push(self.clock.sleep(delay).map(move |_| id).boxed());
+
+		true
+	}
+
+	fn start_subsystem(&mut self, id: SubsystemId) -> SubsystemResult<()> {
+		let named = self.named(id);
+		let res = match id {
+			SubsystemId::Validation => start_instance(
+				&self.s,
+				&mut self.running_subsystems_rx,
+				&self.metrics,
+				&self.blocking_pool,
+				&self.clock,
+				named,
+				&mut self.validation_subsystem,
+			),
+			SubsystemId::CandidateBacking => start_instance(
+				&self.s,
+				&mut self.running_subsystems_rx,
+				&self.metrics,
+				&self.blocking_pool,
+				&self.clock,
+				named,
+				&mut self.candidate_backing_subsystem,
+			),
+		};
+		let handle = res?;
+
+		let generation = self.starts.entry(id).or_insert(0);
+		*generation += 1;
+		let generation = *generation;
+		self.running_subsystems.push(handle.map(move |res| (id, generation, res)).boxed());
+
+		self.metrics.subsystem(id).reset_mailbox();
+		self.heartbeats.insert(id, self.clock.now());
+		self.unhealthy.remove(&id);
+		self.emit(OverseerEvent::SubsystemStarted(id));
+
+		Ok(())
+	}
+
+	fn running_subsystem_ids(&self) -> Vec<SubsystemId> {
+		let mut ids = Vec::new();
+
+		if self.validation_subsystem.instance.is_some() {
+			ids.push(SubsystemId::Validation);
+		}
+
+		if self.candidate_backing_subsystem.instance.is_some() {
+			ids.push(SubsystemId::CandidateBacking);
+		}
+
+		ids
+	}
+
+	fn has_concluded(&self, id: SubsystemId) -> bool {
+		let concluded = match id {
+			SubsystemId::Validation => self.validation_subsystem.instance.as_ref().map(|s| &s.concluded),
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance.as_ref().map(|s| &s.concluded),
+		};
+
+		matches!(concluded, Some(concluded) if concluded.load(Ordering::Relaxed))
+	}
+
+	fn remove_instance(&mut self, id: SubsystemId) {
+		self.ready.remove(&id);
+		self.heartbeats.remove(&id);
+		self.unhealthy.remove(&id);
+		self.lagging.remove(&id);
+
+		match id {
+			SubsystemId::Validation => self.validation_subsystem.instance = None,
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance = None,
+		}
+	}
+
+	// Put a subsystem given through the handler in its place and start it,
+	// once its dependencies are ready if they are waited for.
+	fn spawn_subsystem(&mut self, s: AnySubsystem) -> SubsystemResult<SubsystemId> {
+		let id = s.id();
+		if self.running_subsystem_ids().contains(&id)
+			|| self.restarting.contains(&id)
+			|| self.pending_starts.contains(&id)
+		{
+			return Err(SubsystemError::AlreadyRunning);
+		}
+
+		log::info!("{}Spawning subsystem {:?}", self.prefix(), self.named(id));
+		match s {
+			AnySubsystem::Validation(s) => self.validation_subsystem.subsystem = s,
+			AnySubsystem::CandidateBacking(s) => self.candidate_backing_subsystem.subsystem = s,
+		}
+		self.restarts.remove(&id);
+		self.results.remove(&id);
+
+		let waiting = self.dependencies(id).any(|dependency| !self.ready.contains(&dependency));
+		match self.config.ready_timeout {
+			Some(timeout) if waiting => {
+				self.pending_starts.push(id);
+				self.start_timeouts.push(self.clock.sleep(timeout).map(move |_| id).boxed());
+			}
+			_ => self.start_subsystem(id)?,
+		}
+
+		Ok(id)
+	}
+
+	// Conclude a running subsystem to start another one in its place once it has finished,
+	// keeping the messages to it meanwhile.
+	fn replace_subsystem(&mut self, s: AnySubsystem, tx: oneshot::Sender<SubsystemResult<SubsystemId>>) {
+		let id = s.id();
+		if !self.running_subsystem_ids().contains(&id) {
+			let _ = tx.send(self.spawn_subsystem(s));
+			return;
+		}
+
+		if self.replacing.contains_key(&id) {
+			let _ = tx.send(Err(SubsystemError::AlreadyRunning));
+			return;
+		}
+
+		log::info!("{}Replacing subsystem {:?}", self.prefix(), self.named(id));
+		self.replacing.insert(id, (s, tx));
+
+		// The conclusion is queued behind the messages, so the ones already sent are handled first.
+		match id {
+			SubsystemId::Validation => if let Some(ref mut s) = self.validation_subsystem.instance {
+				s.queue_signal(OverseerSignal::Conclude);
+			},
+			SubsystemId::CandidateBacking => if let Some(ref mut s) = self.candidate_backing_subsystem.instance {
+				s.queue_signal(OverseerSignal::Conclude);
+			},
+		}
+	}
+
+	// Start the replacement of a subsystem that has finished, handing it the messages kept for the old one.
+	//
+	// The old one may have finished before receiving its conclusion, which is not meant for the replacement.
+	fn start_replacement(&mut self, s: AnySubsystem) -> SubsystemResult<()> {
+		let id = s.id();
+		self.restarts.remove(&id);
+
+		match s {
+			AnySubsystem::Validation(s) => {
+				self.validation_subsystem.subsystem = s;
+				let kept = self.validation_subsystem.instance.as_mut()
+					.map(|s| take_kept(&mut s.overflow))
+					.unwrap_or_default();
+				self.remove_instance(id);
+				self.start_subsystem(id)?;
+				if let Some(ref mut s) = self.validation_subsystem.instance {
+					s.overflow = kept;
+				}
+			}
+			AnySubsystem::CandidateBacking(s) => {
+				self.candidate_backing_subsystem.subsystem = s;
+				let kept = self.candidate_backing_subsystem.instance.as_mut()
+					.map(|s| take_kept(&mut s.overflow))
+					.unwrap_or_default();
+				self.remove_instance(id);
+				self.start_subsystem(id)?;
+				if let Some(ref mut s) = self.candidate_backing_subsystem.instance {
+					s.overflow = kept;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	// Whether the mailboxes of the subsystems hold as many messages as the `message_budget` allows.
+	fn is_over_budget(&self) -> bool {
+		match self.config.message_budget {
+			Some(budget) => self.metrics.in_flight() >= budget as u64,
+			None => false,
+		}
+	}
+
+	// Resolves once the mailboxes of the subsystems hold fewer messages than the `message_budget`.
+	fn drained(&self) -> impl Future<Output = ()> + Send + 'static {
+		let metrics = self.metrics.clone();
+		let budget = self.config.message_budget;
+		future::poll_fn(move |cx| {
+			metrics.received.register(cx.waker());
+			match budget {
+				Some(budget) if metrics.in_flight() >= budget as u64 => Poll::Pending,
+				_ => Poll::Ready(()),
+			}
+		})
+	}
+
+	fn prefix(&self) -> LogPrefix {
+		LogPrefix(self.name)
+	}
+
+	fn named(&self, id: SubsystemId) -> Named {
+		Named {
+			overseer: self.name,
+			name: self.names.get(&id).copied(),
+			id,
+		}
+	}
+
+	// Whether the messages to a subsystem are kept in its overflow instead of being delivered.
+	fn is_held(&self, id: SubsystemId) -> bool {
+		self.paused.contains(&id) || self.replacing.contains_key(&id)
+	}
+
+	// Whether the overflow of a subsystem is kept instead of being sent.
+	//
+	// A subsystem being replaced is still sent its overflow up to its conclusion,
+	// what has been kept after that is for its replacement.
+	fn is_overflow_held(&self, id: SubsystemId) -> bool {
+		let concluding = match id {
+			SubsystemId::Validation => self.validation_subsystem.instance.as_ref().map_or(false, |s| s.is_concluding()),
+			SubsystemId::CandidateBacking => self.candidate_backing_subsystem.instance.as_ref().map_or(false, |s| s.is_concluding()),
+		};
+
+		self.paused.contains(&id) || (self.replacing.contains_key(&id) && !concluding)
+	}
+
+	// Whether a handle of the subsystem is from when it has last been started.
+	fn is_current(&self, id: SubsystemId, generation: usize) -> bool {
+		self.starts.get(&id) == Some(&generation)
+	}
+
+	fn spawn_job(&mut self, j: BoxFuture<'static, ()>) -> SubsystemResult<()> {
+		Ok(self.s.spawn(j)?)
+	}
+}
+
+impl<S> Future for Overseer<S>
+where
+	S: Spawn + Send + Sync + 'static,
+{
+	type Output = ShutdownReport;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+		let this = self.get_mut();
+
+		if this.running.is_none() {
+			this.running = Some(this.take().run_loop().boxed().fuse());
+		}
+
+		match &mut this.running {
+			Some(running) => running.poll_unpin(cx),
+			None => Poll::Pending,
+		}
+	}
+}
+
+impl<S> FusedFuture for Overseer<S>
+where
+	S: Spawn + Send + Sync + 'static,
+{
+	fn is_terminated(&self) -> bool {
+		matches!(&self.running, Some(running) if running.is_terminated())
+	}
+}
+
+// Order the subsystems so that each one comes after all of its dependencies.
+fn boot_order(dependencies: &[(SubsystemId, SubsystemId)]) -> SubsystemResult<Vec<SubsystemId>> {
+	let mut order = Vec::with_capacity(SubsystemId::ALL.len());
+
+	while order.len() < SubsystemId::ALL.len() {
+		let next = SubsystemId::ALL.iter().find(|id| {
+			!order.contains(*id) && dependencies.iter().all(|(dependent, dependency)| {
+				dependent != *id || order.contains(dependency)
+			})
+		});
+
+		match next {
+			Some(id) => order.push(*id),
+			None => return Err(SubsystemError::DependencyCycle),
+		}
+	}
+
+	Ok(order)
+}
+
+// What a subsystem has panicked with, as far as it can be told.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+	payload.downcast_ref::<&str>()
+		.copied()
+		.or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+		.unwrap_or("a payload that is not a string")
+}
+
+// Let the other tasks run before going on.
+async fn yield_now() {
+	let mut yielded = false;
+
+	future::poll_fn(|cx| {
+		if yielded {
+			Poll::Ready(())
+		} else {
+			yielded = true;
+			cx.waker().wake_by_ref();
+			Poll::Pending
+		}
+	}).await
+}
+
+// Take the messages kept in an overflow, leaving out the signals to conclude.
+fn take_kept<M: Debug>(overflow: &mut VecDeque<Envelope<M>>) -> VecDeque<Envelope<M>> {
+	std::mem::take(overflow)
+		.into_iter()
+		.filter(|envelope| !matches!(envelope.msg, FromOverseer::Signal(OverseerSignal::Conclude)))
+		.collect()
+}
+
+// Start a new running instance of an overseen subsystem with a fresh context.
+//
+// Returns the handle to await the subsystem finishing with.
+fn start_instance<S: Spawn + Send + Sync + 'static, M: Debug>(
+	spawner: &Arc<S>,
+	streams: &mut StreamUnordered<BoxStream<'static, (SubsystemId, ToOverseer)>>,
+	metrics: &Arc<Metrics>,
+	blocking_pool: &Arc<dyn SpawnBlocking>,
+	clock: &Arc<dyn Clock>,
+	named: Named,
+	s: &mut OverseenSubsystem<M>,
+) -> SubsystemResult<BoxFuture<'static, SubsystemResult<()>>> {
+	let id = named.id;
+	let (to_tx, to_rx) = if s.channels.unbounded_inbound {
+		let (tx, rx) = mpsc::unbounded();
+		(SubsystemSender::Unbounded(tx), Either::Right(rx))
+	} else {
+		let (tx, rx) = mpsc::channel(s.channels.inbound);
+		(SubsystemSender::Bounded(tx), Either::Left(rx))
+	};
+	let (signals_tx, signals_rx) = mpsc::unbounded();
+	let (from_tx, from_rx) = mpsc::channel(s.channels.outbound);
+	let (posts_tx, posts_rx) = mpsc::unbounded();
+	let (cancel, cancelled) = oneshot::channel();
+	let ctx = SubsystemContext::new(
+		id,
+		to_rx,
+		signals_rx,
+		from_tx,
+		posts_tx,
+		spawner.clone(),
+		metrics.clone(),
+		blocking_pool.clone(),
+		clock.clone(),
+		cancelled,
+	);
+	let concluded = ctx.concluded.clone();
+	log::debug!("{}Calling on_start of subsystem {:?}", named.prefix(), named);
+	s.subsystem.on_start();
+	let f = s.subsystem.start(ctx);
+
+	// A panic is turned into an error to be handled by the exit policy instead of
+	// being resumed by the `RemoteHandle` inside of the overseer. The subsystem
+	// is not touched after it has panicked so its state can not be observed
+	// broken, hence it is fine to assert the unwind safety. What it has panicked
+	// with is only logged, as the error has to be cloned for those awaiting it.
+	let f = AssertUnwindSafe(f.0)
+		.catch_unwind()
+		.map(move |res| res.unwrap_or_else(|payload| {
+			log::error!("{}Subsystem {:?} panicked with {}", named.prefix(), named, panic_message(&*payload));
+			Err(SubsystemError::SubsystemPanicked)
+		}));
+
+	// An aborted subsystem is one that has been found to be unhealthy.
+	let (f, abort) = future::abortable(f);
+	let f = f.map(|res| res.unwrap_or(Err(SubsystemError::Unresponsive)));
+
+	let handle = spawner.spawn_with_handle(f)?;
+
+	streams.insert(stream::select(from_rx, posts_rx).map(move |msg| (id, msg)).boxed());
+
+	s.instance = Some(SubsystemInstance {
+		tx: to_tx,
+		signals_tx,
+		abort,
+		cancel,
+		overflow: VecDeque::new(),
+		concluded,
+	});
+
+	Ok(handle.boxed())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
+	use futures::{executor, pending, pin_mut, select, channel::mpsc, FutureExt};
+	use super::*;
+
+	struct TestSubsystem1(mpsc::Sender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for TestSubsystem1 {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let mut i = 0;
+				loop {
+					match ctx.recv().await {
+						Ok(FromOverseer::Communication { .. }) => {
+							let _ = sender.send(i).await;
+							i += 1;
+							continue;
+						}
+						Ok(FromOverseer::Signal(OverseerSignal::StopWork)) => return Ok(()),
+						Err(_) => return Ok(()),
+						_ => (),
+					}
+				}
+			}))
+		}
+	}
+
+	struct TestSubsystem2(mpsc::Sender<usize>);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for TestSubsystem2 {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let mut c: usize = 0;
+				loop {
+					if c < 10 {
+						ctx.send_msg(
+							AllMessages::Validation(
+								ValidationSubsystemMessage::ValidityAttestation
+							)
+						).await.unwrap();
+						let _ = sender.send(c).await;
+						c += 1;
+						continue;
+					}
+					match ctx.try_recv().await {
+						Ok(Some(FromOverseer::Signal(OverseerSignal::StopWork))) => {
+							break;
+						}
+						Ok(Some(_)) => {
+							continue;
+						}
+						Err(_) => return Ok(()),
+						_ => (),
+					}
+					pending!();
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Ignores all signals and only exits once its channel is closed,
+	// reporting that on the given sender.
+	struct ExitsOnClosedChannel(mpsc::Sender<()>);
+
+	impl Subsystem<ValidationSubsystemMessage> for ExitsOnClosedChannel {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				while ctx.recv().await.is_ok() {}
+
+				let _ = sender.send(()).await;
+
+				Ok(())
+			}))
+		}
+	}
+
+	struct TestSubsystem4;
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for TestSubsystem4 {
+		fn start(&mut self, mut _ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				// Do nothing and fail.
+				Err(SubsystemError::ChannelClosed)
+			}))
+		}
+	}
+
+	// Concludes as soon as it is started.
+	struct ConcludesAtOnce;
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for ConcludesAtOnce {
+		fn start(&mut self, mut _ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				// Do nothing and conclude.
+				Ok(())
+			}))
+		}
+	}
+
+	// Reports the number of times it has been started and fails on the first start.
+	struct FailsOnFirstStart(Arc<AtomicUsize>, mpsc::Sender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for FailsOnFirstStart {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let starts = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+			let mut sender = self.1.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let _ = sender.send(starts).await;
+
+				if starts == 1 {
+					return Err(SubsystemError::ChannelClosed);
+				}
+
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Reports the number of times it has been started and always fails.
+	struct AlwaysFails(Arc<AtomicUsize>, mpsc::Sender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for AlwaysFails {
+		fn start(&mut self, _ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let starts = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+			let mut sender = self.1.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let _ = sender.send(starts).await;
+
+				Err(SubsystemError::ChannelClosed)
+			}))
+		}
+	}
+
+	// Stays idle until its channel is closed.
+	struct IdleSubsystem;
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for IdleSubsystem {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Panics upon receiving a message.
+	struct PanicsOnMessage;
+
+	impl Subsystem<ValidationSubsystemMessage> for PanicsOnMessage {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				loop {
+					if let FromOverseer::Communication { .. } = ctx.recv().await? {
+						panic!("PanicsOnMessage received a message");
+					}
+				}
+			}))
+		}
+	}
+
+	// Reports the id of its context.
+	struct ReportsId(mpsc::Sender<SubsystemId>);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for ReportsId {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let _ = sender.send(ctx.id()).await;
+
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Never reads its messages, only waits for a signal and reports it.
+	struct AwaitsSignal(mpsc::Sender<OverseerSignal>);
+
+	impl Subsystem<ValidationSubsystemMessage> for AwaitsSignal {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let signal = ctx.recv_signal().await?;
+				let _ = sender.send(signal).await;
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Slowly processes messages and reports how many there were upon `Conclude`.
+	struct SlowCounter(mpsc::Sender<usize>);
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for SlowCounter {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			let mut sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let mut processed = 0;
+				loop {
+					match ctx.recv().await? {
+						FromOverseer::Communication { .. } => {
+							Delay::new(Duration::from_millis(10)).await;
+							processed += 1;
+						}
+						FromOverseer::Signal(OverseerSignal::Conclude) => {
+							let _ = sender.send(processed).await;
+							return Ok(());
+						}
+						_ => (),
+					}
+				}
+			}))
+		}
+	}
+
+	// Reports the id of its context as soon as it is started.
+	struct ReportsIdOnStart(mpsc::UnboundedSender<SubsystemId>);
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for ReportsIdOnStart {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			let _ = self.0.unbounded_send(ctx.id());
+			SpawnedSubsystem(Box::pin(async move {
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Signals it is ready upon receiving the first message.
+	struct ReadyOnFirstMessage;
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for ReadyOnFirstMessage {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				ctx.recv().await?;
+				ctx.signal_ready().await?;
+
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Sends a heartbeat every 10ms until it is told to conclude.
+	struct Heartbeating;
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for Heartbeating {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				loop {
+					match ctx.try_recv().await {
+						Ok(Some(FromOverseer::Signal(OverseerSignal::Conclude))) | Err(()) => return Ok(()),
+						_ => (),
+					}
+
+					ctx.heartbeat().await?;
+					Delay::new(Duration::from_millis(10)).await;
+				}
+			}))
+		}
+	}
+
+	// Forwards every message it receives to the candidate backing subsystem.
+	struct ForwardsToCandidateBacking;
+
+	impl Subsystem<ValidationSubsystemMessage> for ForwardsToCandidateBacking {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				loop {
+					if let FromOverseer::Communication { .. } = ctx.recv().await? {
+						ctx.send_msg(AllMessages::CandidateBacking(
+							CandidateBackingSubsystemMessage::Second
+						)).await?;
+					}
+				}
+			}))
+		}
+	}
+
+	// Reports the id of its context for every message it receives.
+	#[cfg(feature = "record")]
+	struct ReportsIdPerMessage(mpsc::UnboundedSender<SubsystemId>);
+
+	#[cfg(feature = "record")]
+	impl<M: Debug + Send + 'static> Subsystem<M> for ReportsIdPerMessage {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			let sender = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Communication { .. } = msg {
+						let _ = sender.unbounded_send(ctx.id());
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Floods the candidate backing subsystem with messages, upon receiving a message
+	// reports how many of them the candidate backing subsystem has received.
+	struct FloodsCandidateBacking(mpsc::UnboundedSender<usize>, Arc<AtomicUsize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for FloodsCandidateBacking {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let sender = self.0.clone();
+			let received = self.1.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				loop {
+					match ctx.try_recv().await {
+						Ok(Some(FromOverseer::Communication { .. })) => {
+							let _ = sender.unbounded_send(received.load(Ordering::SeqCst));
+						}
+						Ok(Some(FromOverseer::Signal(OverseerSignal::Conclude))) | Err(()) => return Ok(()),
+						_ => (),
+					}
+
+					ctx.send_msg(AllMessages::CandidateBacking(
+						CandidateBackingSubsystemMessage::Second
+					)).await?;
+				}
+			}))
+		}
+	}
+
+	// Counts the messages it receives and sends a single message upon the 100th one.
+	struct CountsToHundred(Arc<AtomicUsize>);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for CountsToHundred {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let received = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Communication { .. } = msg {
+						if received.fetch_add(1, Ordering::SeqCst) + 1 == 100 {
+							ctx.send_msg(AllMessages::Validation(
+								ValidationSubsystemMessage::ValidityAttestation
+							)).await?;
+						}
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Computes a value, reports it and concludes.
+	struct ComputesValue(mpsc::UnboundedSender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for ComputesValue {
+		fn start(&mut self, mut _ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				tx.unbounded_send((1..=10).sum()).unwrap();
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Reports the result of some blocking work and concludes.
+	struct RunsBlockingWork(mpsc::UnboundedSender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for RunsBlockingWork {
+		fn start(&mut self, ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let value = ctx.spawn_blocking(|| {
+					std::thread::sleep(Duration::from_millis(10));
+					42
+				}).await?;
+				tx.unbounded_send(value).unwrap();
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Spawns 100 short tasks, each reporting when it is done, and waits for the conclusion.
+	struct SpawnsTasks(mpsc::UnboundedSender<usize>);
+
+	impl Subsystem<ValidationSubsystemMessage> for SpawnsTasks {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				for i in 0..100 {
+					let tx = tx.clone();
+					ctx.spawn_task(async move {
+						Delay::new(Duration::from_millis(1)).await;
+						tx.unbounded_send(i).unwrap();
+					})?;
+				}
+
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Signal(OverseerSignal::Conclude) = msg {
+						break;
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Races a long operation against its cancellation, reporting if it has been cancelled.
+	struct RacesCancellation(mpsc::UnboundedSender<()>);
+
+	impl Subsystem<ValidationSubsystemMessage> for RacesCancellation {
+		fn start(&mut self, ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let mut operation = Delay::new(Duration::from_secs(60)).fuse();
+				let mut cancelled = ctx.cancelled().fuse();
+				select! {
+					_ = operation => (),
+					_ = cancelled => tx.unbounded_send(()).unwrap(),
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Does not receive anything until it is let go, then reports every message it receives.
+	struct GatedReceiver(Option<oneshot::Receiver<()>>, mpsc::UnboundedSender<()>);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for GatedReceiver {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let go = self.0.take().unwrap();
+			let tx = self.1.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				let _ = go.await;
+
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Communication { .. } = msg {
+						tx.unbounded_send(()).unwrap();
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Never concludes, whatever it is told.
+	struct NeverConcludes;
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for NeverConcludes {
+		fn start(&mut self, _ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(future::pending()))
+		}
+	}
+
+	// Reports the id of every message it receives.
+	struct ReportsMessageIds(mpsc::UnboundedSender<Option<MessageId>>);
+
+	impl<M: Debug + Send + 'static> Subsystem<M> for ReportsMessageIds {
+		fn start(&mut self, mut ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Communication { .. } = msg {
+						tx.unbounded_send(ctx.current_message_id()).unwrap();
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Sends runs of one to three `Second`s, each followed by a `RegisterBackingWatcher`.
+	struct SendsRunsOfSeconds;
+
+	impl Subsystem<ValidationSubsystemMessage> for SendsRunsOfSeconds {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			SpawnedSubsystem(Box::pin(async move {
+				for i in 0..100 {
+					for _ in 0..=i % 3 {
+						ctx.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await?;
+					}
+					ctx.send_msg(AllMessages::CandidateBacking(
+						CandidateBackingSubsystemMessage::RegisterBackingWatcher
+					)).await?;
+				}
+
+				while ctx.recv().await.is_ok() {}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Reports every message it receives, `true` for a `Second`.
+	struct ReportsSeconds(mpsc::UnboundedSender<bool>);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for ReportsSeconds {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let tx = self.0.clone();
+			SpawnedSubsystem(Box::pin(async move {
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Communication { msg } = msg {
+						let second = matches!(msg, CandidateBackingSubsystemMessage::Second);
+						tx.unbounded_send(second).unwrap();
+					}
+				}
+
+				Ok(())
+			}))
+		}
+	}
+
+	// Concludes after it has received the given number of messages.
+	struct ConcludesAfter(usize);
+
+	impl Subsystem<CandidateBackingSubsystemMessage> for ConcludesAfter {
+		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+			let mut left = self.0;
+			SpawnedSubsystem(Box::pin(async move {
+				while left > 0 {
+					if let FromOverseer::Communication { .. } = ctx.recv().await? {
+						left -= 1;
+					}
+				}
+
+				ctx.conclude()
+			}))
+		}
+	}
+
+	// Reports the calls of its lifecycle hooks and when its future concludes.
+	struct ReportsLifecycle(&'static str, mpsc::UnboundedSender<(&'static str, &'static str)>);
+
+	impl Subsystem<ValidationSubsystemMessage> for ReportsLifecycle {
+		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+			let (name, tx) = (self.0, self.1.clone());
+			tx.unbounded_send((name, "start")).unwrap();
+
+			SpawnedSubsystem(Box::pin(async move {
+				while let FromOverseer::Communication { .. } = ctx.recv().await? {}
+
+				tx.unbounded_send((name, "concluded")).unwrap();
+				ctx.conclude()
+			}))
+		}
+
+		fn on_start(&mut self) {
+			self.1.unbounded_send((self.0, "on_start")).unwrap();
+		}
+
+		fn on_stop(&mut self) {
+			self.1.unbounded_send((self.0, "on_stop")).unwrap();
+		}
+	}
+
+	// Writes into a channel, to stand in for a pipe to another process.
+	#[cfg(feature = "proxy")]
+	struct ChannelWriter(mpsc::UnboundedSender<std::io::Result<Vec<u8>>>);
+
+	#[cfg(feature = "proxy")]
+	impl futures::io::AsyncWrite for ChannelWriter {
+		fn poll_write(self: Pin<&mut Self>, _: &mut Context, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+			match self.0.unbounded_send(Ok(buf.to_vec())) {
+				Ok(()) => Poll::Ready(Ok(buf.len())),
+				Err(_) => Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into())),
+			}
+		}
+
+		fn poll_flush(self: Pin<&mut Self>, _: &mut Context) -> Poll<std::io::Result<()>> {
+			Poll::Ready(Ok(()))
+		}
+
+		fn poll_close(self: Pin<&mut Self>, _: &mut Context) -> Poll<std::io::Result<()>> {
+			self.0.close_channel();
+			Poll::Ready(Ok(()))
+		}
+	}
+
+	// Counts the blocking work it runs.
+	#[derive(Debug)]
+	struct CountingPool(ThreadPool, Arc<AtomicUsize>);
+
+	impl SpawnBlocking for CountingPool {
+		fn spawn_blocking(&self, f: Box<dyn FnOnce() + Send>) {
+			self.1.fetch_add(1, Ordering::SeqCst);
+			self.0.spawn_blocking(f);
+		}
+	}
+
+	// The other ends of the channels of a `SubsystemContext` that is tested without an overseer.
+	struct ContextChannels<M: Debug> {
+		to_tx: mpsc::UnboundedSender<Envelope<M>>,
+		signals_tx: mpsc::UnboundedSender<OverseerSignal>,
+		from_rx: mpsc::Receiver<ToOverseer>,
+		posts_rx: mpsc::UnboundedReceiver<ToOverseer>,
+	}
+
+	// A context for a subsystem that is not run by an overseer, with `capacity` messages
+	// of room in its channel to the overseer, along with the other ends of its channels.
+	fn test_context<M: Debug>(id: SubsystemId, capacity: usize) -> (SubsystemContext<M>, ContextChannels<M>) {
+		let (to_tx, to_rx) = mpsc::unbounded();
+		let (signals_tx, signals_rx) = mpsc::unbounded();
+		let (from_tx, from_rx) = mpsc::channel(capacity);
+		let (posts_tx, posts_rx) = mpsc::unbounded();
+		let ctx = SubsystemContext::new(
+			id,
+			Either::Right(to_rx),
+			signals_rx,
+			from_tx,
+			posts_tx,
+			Arc::new(ThreadPool::new().unwrap()),
+			Arc::new(Metrics::default()),
+			Arc::new(ThreadPool::new().unwrap()),
+			Arc::new(SystemClock),
+			oneshot::channel().1,
+		);
+
+		(ctx, ContextChannels { to_tx, signals_tx, from_rx, posts_rx })
+	}
+
+	// Checks that a minimal configuration of two jobs can run and exchange messages.
+	#[test]
+	fn overseer_works() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::channel(64);
+
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+
+			pin_mut!(overseer_fut);
+
+			let mut s1_results = Vec::new();
+			let mut s2_results = Vec::new();
+
+			loop {
+				select! {
+					_ = overseer_fut => break,
+					s1_next = s1_rx.next() => {
+						match s1_next {
+							Some(msg) => {
+								s1_results.push(msg);
+								if s1_results.len() == 10 {
+									handler.stop().await.unwrap();
+								}
+							}
+							None => break,
+						}
+					},
+					s2_next = s2_rx.next() => {
+						match s2_next {
+							Some(msg) => s2_results.push(msg),
+							None => break,
+						}
+					},
+					complete => break,
+				}
+			}
+
+			assert_eq!(s1_results, (0..10).collect::<Vec<_>>());
+		});
+	}
+
+	// Checks that the span a message is received in follows from the span
+	// of the message that the sender was handling.
+	#[cfg(feature = "tracing")]
+	#[test]
+	fn message_spans_follow_from_each_other() {
+		// Records which spans follow from which, by their ids.
+		struct FollowsFromRecorder(std::sync::Mutex<Vec<(u64, u64)>>, std::sync::atomic::AtomicU64);
+
+		impl tracing::Subscriber for FollowsFromRecorder {
+			fn enabled(&self, _: &tracing::Metadata) -> bool {
+				true
+			}
+
+			fn new_span(&self, _: &tracing::span::Attributes) -> tracing::span::Id {
+				tracing::span::Id::from_u64(self.1.fetch_add(1, Ordering::SeqCst) + 1)
+			}
+
+			fn record(&self, _: &tracing::span::Id, _: &tracing::span::Record) {}
+
+			fn record_follows_from(&self, span: &tracing::span::Id, follows: &tracing::span::Id) {
+				self.0.lock().unwrap().push((span.into_u64(), follows.into_u64()));
+			}
+
+			fn event(&self, _: &tracing::Event) {}
+
+			fn enter(&self, _: &tracing::span::Id) {}
+
+			fn exit(&self, _: &tracing::span::Id) {}
+		}
+
+		// Reports the span of every message and forwards it to the candidate backing subsystem.
+		struct Forwarder(mpsc::UnboundedSender<Option<tracing::span::Id>>);
+
+		impl Subsystem<ValidationSubsystemMessage> for Forwarder {
+			fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
+				let tx = self.0.clone();
+				SpawnedSubsystem(Box::pin(async move {
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(ctx.span().id()).unwrap();
+						ctx.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await?;
+					}
+
+					Ok(())
+				}))
+			}
+		}
+
+		// Reports the span of every message.
+		struct Receiver(mpsc::UnboundedSender<Option<tracing::span::Id>>);
+
+		impl Subsystem<CandidateBackingSubsystemMessage> for Receiver {
+			fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
+				let tx = self.0.clone();
+				SpawnedSubsystem(Box::pin(async move {
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(ctx.span().id()).unwrap();
+					}
+
+					Ok(())
+				}))
+			}
+		}
+
+		let recorder = Arc::new(FollowsFromRecorder(Default::default(), Default::default()));
+		tracing::subscriber::set_global_default(recorder.clone()).unwrap();
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(Forwarder(tx.clone())),
+				Box::new(Receiver(tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			let mut spans = Vec::new();
+			while spans.len() < 2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					span = rx.next() => spans.push(span.unwrap().expect("the span is enabled").into_u64()),
+				}
+			}
+
+			assert!(recorder.0.lock().unwrap().contains(&(spans[1], spans[0])));
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Spawn a subsystem that immediately fails.
+	//
+	// Should immediately conclude the overseer itself with an error.
+	#[test]
+	fn overseer_panics_on_sybsystem_exit() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _) = mpsc::channel(64);
+			let (overseer, _handle) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem4),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				report = overseer_fut => match report.reason {
+					ShutdownReason::SubsystemFatal(..) => (),
+					reason => panic!("Unexpected shutdown reason {:?}", reason),
+				},
+				complete => (),
+			}
+		})
+	}
+
+	// Stop the overseer while a subsystem ignores the `Conclude` signal.
+	//
+	// The subsystem should still observe its channel being closed.
+	#[test]
+	fn overseer_stop_closes_subsystem_channels() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ExitsOnClosedChannel(s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+			).unwrap();
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer.run().await.reason, ShutdownReason::ExternalStop);
+			assert_eq!(s1_rx.next().await, Some(()));
+			assert!(handler.stop().await.is_err());
+		})
+	}
+
+	// Spawn a subsystem that immediately concludes.
+	//
+	// The overseer should keep running the other subsystems.
+	#[test]
+	fn overseer_keeps_running_on_subsystem_conclusion() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(ConcludesAtOnce),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				msg = s1_rx.next() => assert_eq!(msg, Some(0)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Spawn a subsystem that fails on its first start with the `Restart` policy.
+	//
+	// The subsystem should be started again.
+	#[test]
+	fn overseer_restarts_failed_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Restart,
+				restart_policy: None,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(FailsOnFirstStart(Arc::new(AtomicUsize::new(0)), s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			for expected in 1..=2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					starts = s1_rx.next() => assert_eq!(starts, Some(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Spawn a subsystem that immediately fails with the `Ignore` policy.
+	//
+	// The overseer should keep running the other subsystems.
+	#[test]
+	fn overseer_ignores_failed_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				restart_policy: None,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem4),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				msg = s1_rx.next() => assert_eq!(msg, Some(0)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Spawn a subsystem that always fails with a `RestartPolicy` of two retries.
+	//
+	// The subsystem should be restarted twice and then left dead.
+	#[test]
+	fn overseer_gives_up_restarting_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				restart_policy: Some(RestartPolicy {
+					max_retries: 2,
+					base_delay: Duration::from_millis(1),
+					max_delay: Duration::from_millis(10),
+				}),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(AlwaysFails(Arc::new(AtomicUsize::new(0)), s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			for expected in 1..=3 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					starts = s1_rx.next() => assert_eq!(starts, Some(expected)),
+				}
+			}
+
+			let mut timeout = Delay::new(Duration::from_millis(100)).fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				starts = s1_rx.next() => panic!("Unexpected restart {:?}", starts),
+				_ = timeout => (),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	#[test]
+	fn restart_policy_delay_is_capped() {
+		let policy = RestartPolicy {
+			max_retries: 100,
+			base_delay: Duration::from_millis(10),
+			max_delay: Duration::from_secs(1),
+		};
+
+		assert_eq!(policy.delay(0), Duration::from_millis(10));
+		assert_eq!(policy.delay(3), Duration::from_millis(80));
+		assert_eq!(policy.delay(7), Duration::from_secs(1));
+		assert_eq!(policy.delay(64), Duration::from_secs(1));
+	}
+
+	// Run the overseer with tiny channels.
+	//
+	// Messages should still be delivered, just with more backpressure.
+	#[test]
+	fn overseer_works_with_small_channels() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let channels = ChannelConfig {
+				inbound: 1,
+				outbound: 1,
+				..Default::default()
+			};
+			let config = OverseerConfig {
+				validation_channels: channels.clone(),
+				candidate_backing_channels: channels,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			for expected in 0..10 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					msg = s1_rx.next() => assert_eq!(msg, Some(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Run the overseer with an unbounded channel to a subsystem.
+	#[test]
+	fn overseer_works_with_unbounded_channels() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let config = OverseerConfig {
+				validation_channels: ChannelConfig {
+					unbounded_inbound: true,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			for expected in 0..10 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					msg = s1_rx.next() => assert_eq!(msg, Some(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Query the running subsystems through the handler.
+	#[test]
+	fn overseer_lists_running_subsystems() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _) = mpsc::channel(64);
+			let (s2_tx, _) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(TestSubsystem2(s2_tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				ids = handler.list_subsystems().fuse() => assert_eq!(
+					ids.unwrap(),
+					vec![SubsystemId::Validation, SubsystemId::CandidateBacking],
+				),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Subscribe to the overseer events and observe a dispatch and a shutdown.
+	#[test]
+	fn overseer_emits_events() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				msg = s1_rx.next() => assert_eq!(msg, Some(0)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			assert_eq!(events.collect::<Vec<_>>().await, vec![
+				OverseerEvent::MessageDispatched {
+					id: MessageId(0),
+					causation_id: None,
+					from: None,
+					to: SubsystemId::Validation,
+				},
+				OverseerEvent::SubsystemStopped(SubsystemId::Validation),
+				OverseerEvent::SubsystemStopped(SubsystemId::CandidateBacking),
+			]);
+		})
+	}
+
+	// Make a subsystem panic with the `Ignore` policy.
+	//
+	// The overseer should survive and report the failure.
+	#[test]
+	fn overseer_survives_subsystem_panic() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(PanicsOnMessage),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			for expected in &[
+				OverseerEvent::MessageDispatched {
+					id: MessageId(0),
+					causation_id: None,
+					from: None,
+					to: SubsystemId::Validation,
+				},
+				OverseerEvent::SubsystemFailed(SubsystemId::Validation),
+			] {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => assert_eq!(event.as_ref(), Some(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	#[test]
+	fn send_and_recv_awaits_reply() {
+		executor::block_on(async move {
+			let (mut ctx, mut channels) = test_context::<ValidationSubsystemMessage>(SubsystemId::Validation, 64);
+
+			let reply = ctx.send_and_recv(|tx| {
+				tx.send(42).unwrap();
+				AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)
+			}).await;
+			assert_eq!(reply.unwrap(), 42);
+
+			let reply = ctx.send_and_recv::<usize>(|_| {
+				AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)
+			}).await;
+			match reply {
+				Err(SubsystemError::ChannelClosed) => (),
+				reply => panic!("Unexpected reply {:?}", reply),
+			}
+
+			for _ in 0..2 {
+				match channels.from_rx.next().await {
+					Some(ToOverseer::SubsystemMessage { msg: AllMessages::Validation(_), .. }) => (),
+					msg => panic!("Unexpected message {:?}", msg),
+				}
+			}
+		})
+	}
+
+	#[test]
+	fn subsystem_knows_its_id() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(ReportsId(s2_tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				id = s2_rx.next() => assert_eq!(id, Some(SubsystemId::CandidateBacking)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Send a message to a subsystem that has failed with the `Ignore` policy.
+	//
+	// The message should be reported as undeliverable.
+	#[test]
+	fn overseer_reports_undeliverable_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(PanicsOnMessage),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			let expected = vec![
+				vec![
+					OverseerEvent::MessageDispatched {
+						id: MessageId(0),
+						causation_id: None,
+						from: None,
+						to: SubsystemId::Validation,
+					},
+					OverseerEvent::SubsystemFailed(SubsystemId::Validation),
+				],
+				vec![
+					OverseerEvent::MessageUndeliverable { from: None, to: SubsystemId::Validation },
+				],
+			];
+
+			for expected in expected {
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await.unwrap();
+
+				for expected in expected {
+					select! {
+						_ = overseer_fut => panic!("Overseer exited early"),
+						event = events.next() => assert_eq!(event, Some(expected)),
+					}
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Send a message to a subsystem that has failed with a dead letter channel.
+	#[test]
+	fn overseer_forwards_dead_letters() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (dead_tx, mut dead_rx) = mpsc::channel(64);
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				dead_letters: Some(dead_tx),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(PanicsOnMessage),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			loop {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => match event {
+						Some(OverseerEvent::SubsystemFailed(_)) => break,
+						Some(_) => (),
+						None => panic!("Events stream ended"),
+					},
+				}
+			}
+
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				letter = dead_rx.next() => match letter {
+					Some((SubsystemId::Validation, AllMessages::Validation(_))) => (),
+					letter => panic!("Unexpected dead letter {:?}", letter),
+				},
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Stop the overseer while a subsystem has unprocessed messages.
+	//
+	// The subsystem should still receive the `Conclude` signal.
+	#[test]
+	fn overseer_signals_subsystem_with_pending_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(AwaitsSignal(s1_tx)),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				event = events.next() => assert!(event.is_some()),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			match s1_rx.next().await {
+				Some(OverseerSignal::Conclude) => (),
+				signal => panic!("Unexpected signal {:?}", signal),
+			}
+		})
+	}
+
+	// Drain the overseer with messages still queued for a subsystem.
+	//
+	// All of them should be processed before the overseer exits, while
+	// the messages sent after draining are rejected.
+	#[test]
+	fn overseer_drains_pending_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(SlowCounter(s1_tx)),
+				Box::new(SlowCounter(s2_tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+
+			for _ in 0..10 {
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await.unwrap();
+			}
+
+			handler.drain().await.unwrap();
+			assert_eq!(
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await,
+				Err(SubsystemError::Draining),
+			);
+			match handler.try_send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)) {
+				Err(TrySendError { error: SubsystemError::Draining, .. }) => (),
+				res => panic!("Unexpected result {:?}", res),
+			}
+
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::Clean);
+			assert_eq!(s1_rx.next().await, Some(10));
+			assert_eq!(s2_rx.next().await, Some(0));
+
+			let mut undeliverable = 0;
+			while let Some(event) = events.next().await {
+				if let OverseerEvent::MessageUndeliverable { .. } = event {
+					undeliverable += 1;
+				}
+			}
+			assert_eq!(undeliverable, 0);
+		})
+	}
+
+	// Drain the overseer right after sending messages that are still in its channel,
+	// or in the overflow of a subsystem whose own channel is full.
+	//
+	// Every message sent before draining should be delivered.
+	#[test]
+	fn overseer_delivers_messages_queued_before_drain() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::channel(64);
+			let channels = ChannelConfig {
+				inbound: 1,
+				..Default::default()
+			};
+			let config = OverseerConfig {
+				validation_channels: channels.clone(),
+				candidate_backing_channels: channels,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(SlowCounter(s1_tx)),
+				Box::new(SlowCounter(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+
+			for _ in 0..20 {
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await.unwrap();
+				handler.send_msg(AllMessages::CandidateBacking(
+					CandidateBackingSubsystemMessage::Second
+				)).await.unwrap();
+			}
+			handler.drain().await.unwrap();
+
+			assert_eq!(overseer.run().await.reason, ShutdownReason::Clean);
+			assert_eq!(s1_rx.next().await, Some(20));
+			assert_eq!(s2_rx.next().await, Some(20));
+			assert_eq!(handler.metrics().undeliverable, 0);
+		})
+	}
+
+	// Checks that the subsystems are started after their dependencies.
+	#[test]
+	fn overseer_starts_dependencies_first() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		let boot = |dependencies| {
+			let (tx, mut rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				dependencies,
+				..Default::default()
+			};
+			Overseer::with_config(
+				Box::new(ReportsIdOnStart(tx.clone())),
+				Box::new(ReportsIdOnStart(tx)),
+				spawner.clone(),
+				config,
+			).map(|_| {
+				let mut order = Vec::new();
+				while let Ok(id) = rx.try_recv() {
+					order.push(id);
+				}
+				order
+			})
+		};
+
+		assert_eq!(
+			boot(vec![]).unwrap(),
+			vec![SubsystemId::Validation, SubsystemId::CandidateBacking],
+		);
+		assert_eq!(
+			boot(vec![(SubsystemId::Validation, SubsystemId::CandidateBacking)]).unwrap(),
+			vec![SubsystemId::CandidateBacking, SubsystemId::Validation],
+		);
+
+		match boot(vec![
+			(SubsystemId::Validation, SubsystemId::CandidateBacking),
+			(SubsystemId::CandidateBacking, SubsystemId::Validation),
+		]) {
+			Err(SubsystemError::DependencyCycle) => (),
+			res => panic!("Unexpected result {:?}", res),
+		}
+	}
+
+	// Checks that a subsystem is started once its dependency is ready and
+	// that the overseer fails if that does not happen in time.
+	#[test]
+	fn overseer_waits_for_ready_dependencies() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				dependencies: vec![(SubsystemId::Validation, SubsystemId::CandidateBacking)],
+				ready_timeout: Some(Duration::from_millis(100)),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(tx)),
+				Box::new(ReadyOnFirstMessage),
+				spawner.clone(),
+				config.clone(),
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			assert!(rx.try_recv().unwrap_err().is_empty());
+
+			handler.send_msg(AllMessages::CandidateBacking(
+				CandidateBackingSubsystemMessage::Second
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_ready(SubsystemId::CandidateBacking).fuse() => assert!(res.is_ok()),
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				id = rx.next() => assert_eq!(id, Some(SubsystemId::Validation)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			let (tx, _rx) = mpsc::unbounded();
+			let (overseer, _handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(tx)),
+				Box::new(ReadyOnFirstMessage),
+				spawner,
+				config,
+			).unwrap();
+
+			match overseer.run().await.reason {
+				ShutdownReason::SubsystemFatal(_, SubsystemError::DependencyTimeout) => (),
+				reason => panic!("Unexpected shutdown reason {:?}", reason),
+			}
+		})
+	}
+
+	// Checks that a subsystem without heartbeats is reported as unhealthy
+	// while a subsystem sending them is not.
+	#[test]
+	fn overseer_reports_unhealthy_subsystems() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, _rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				liveness_timeout: Some(Duration::from_millis(50)),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(tx)),
+				Box::new(Heartbeating),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				event = events.next() => assert_eq!(
+					event,
+					Some(OverseerEvent::SubsystemUnhealthy(SubsystemId::Validation)),
+				),
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				health = handler.health().fuse() => {
+					let health = health.unwrap();
+					assert_eq!(health.get(&SubsystemId::Validation), Some(&Health::Unhealthy));
+					assert_eq!(health.get(&SubsystemId::CandidateBacking), Some(&Health::Healthy));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that the messages a subsystem has not received yet are counted.
+	#[test]
+	fn overseer_counts_pending_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _s1_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(AwaitsSignal(s1_tx)),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+
+			for _ in 0..3 {
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await.unwrap();
+			}
+
+			for i in 0..3 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => assert_eq!(
+						event,
+						Some(OverseerEvent::MessageDispatched {
+							id: MessageId(i),
+							causation_id: None,
+							from: None,
+							to: SubsystemId::Validation,
+						}),
+					),
+				}
+			}
+
+			let metrics = handler.metrics();
+			assert_eq!(
+				metrics.subsystems[&SubsystemId::Validation],
+				SubsystemMetricsSnapshot { sent: 0, received: 0, mailbox_depth: 3, lagging: 0 },
+			);
+			assert_eq!(metrics.undeliverable, 0);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a message sent while handling another one records it as its cause.
+	#[test]
+	fn overseer_links_caused_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ForwardsToCandidateBacking),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(
+				ValidationSubsystemMessage::ValidityAttestation
+			)).await.unwrap();
+
+			for expected in &[
+				OverseerEvent::MessageDispatched {
+					id: MessageId(0),
+					causation_id: None,
+					from: None,
+					to: SubsystemId::Validation,
+				},
+				OverseerEvent::MessageDispatched {
+					id: MessageId(1),
+					causation_id: Some(MessageId(0)),
+					from: Some(SubsystemId::Validation),
+					to: SubsystemId::CandidateBacking,
+				},
+			] {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => assert_eq!(event.as_ref(), Some(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Record the messages of a run and replay them against fresh subsystems.
+	//
+	// The replayed subsystems should receive the same messages and
+	// the ones they send themselves should not be delivered again.
+	#[cfg(feature = "record")]
+	#[test]
+	fn overseer_replays_recorded_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (recorder, records) = mpsc::unbounded();
+			let config = OverseerConfig {
+				recorder: Some(recorder),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ForwardsToCandidateBacking),
+				Box::new(IdleSubsystem),
+				spawner.clone(),
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+
+			for _ in 0..2 {
+				handler.send_msg(AllMessages::Validation(
+					ValidationSubsystemMessage::ValidityAttestation
+				)).await.unwrap();
+			}
+
+			for _ in 0..4 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => assert!(event.is_some()),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			let records = records.collect::<Vec<_>>().await;
+			assert_eq!(records.len(), 4);
+			assert_eq!(records.iter().filter(|r| r.to == SubsystemId::CandidateBacking).count(), 2);
+
+			let (tx, rx) = mpsc::unbounded();
+			let (overseer, _handler) = Overseer::new(
+				Box::new(ForwardsToCandidateBacking),
+				Box::new(ReportsIdPerMessage(tx)),
+				spawner,
+			).unwrap();
+
+			assert!(overseer.replay(records).await.is_ok());
+			assert_eq!(
+				rx.collect::<Vec<_>>().await,
+				vec![SubsystemId::CandidateBacking, SubsystemId::CandidateBacking],
+			);
+		})
+	}
+
+	// Checks that the queued messages are taken by their priority,
+	// while a waiting message of a lower priority is not starved.
+	#[test]
+	fn overseer_dispatches_by_priority() {
+		let spawner = executor::ThreadPool::new().unwrap();
+		let (tx, _rx) = mpsc::unbounded();
+		let (mut overseer, _handler) = Overseer::new(
+			Box::new(ReportsIdOnStart(tx.clone())),
+			Box::new(ReportsIdOnStart(tx)),
+			spawner,
+		).unwrap();
+
+		let mut queue = |priority: Priority, msg| {
+			overseer.queued[priority as usize].push_back(QueuedMessage {
+				from: SubsystemId::Validation,
+				causation_id: None,
+				msg,
+				deadline: None,
+				span: MessageSpan::none(),
+			});
+		};
+
+		for _ in 0..10 {
+			queue(Priority::High, AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation));
+		}
+		queue(Priority::Normal, AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second));
+		queue(Priority::Low, AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher));
+
+		let mut order = Vec::new();
+		while let Some(queued) = overseer.next_queued() {
+			order.push(match queued.msg {
+				AllMessages::Validation(_) => Priority::High,
+				AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second) => Priority::Normal,
+				AllMessages::CandidateBacking(_) => Priority::Low,
+			});
+		}
+
+		let mut expected = vec![Priority::High; MAX_PRIORITY_STREAK];
+		expected.extend_from_slice(&[Priority::Low, Priority::Normal, Priority::High, Priority::High]);
+		assert_eq!(order, expected);
+	}
+
+	// Checks that a message of a quiet subsystem does not wait behind
+	// the whole backlog of a subsystem flooding the overseer.
+	#[test]
+	fn overseer_dispatches_fairly() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let received = Arc::new(AtomicUsize::new(0));
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 8,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(FloodsCandidateBacking(tx, received.clone())),
+				Box::new(CountsToHundred(received)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				flooded = rx.next() => {
+					let flooded = flooded.unwrap();
+					assert!(flooded < 100 + CHANNEL_CAPACITY / 8, "Waited behind {} messages", flooded - 100);
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the result a subsystem has finished with can be awaited,
+	// both while it is running and after it has finished.
+	#[test]
+	fn overseer_awaits_subsystems() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ComputesValue(tx)),
+				Box::new(TestSubsystem4),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Ok(())),
+			}
+			assert_eq!(rx.next().await, Some(55));
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::CandidateBacking).fuse() => {
+					assert_eq!(res, Err(SubsystemError::ChannelClosed));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that blocking work is run on the configured pool.
+	#[test]
+	fn subsystem_spawns_blocking_work() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let spawned = Arc::new(AtomicUsize::new(0));
+			let config = OverseerConfig {
+				blocking_pool: Some(Arc::new(CountingPool(ThreadPool::new().unwrap(), spawned.clone()))),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(RunsBlockingWork(tx)),
+				Box::new(ConcludesAtOnce),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Ok(())),
+			}
+			assert_eq!(rx.next().await, Some(42));
+			assert_eq!(spawned.load(Ordering::SeqCst), 1);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the tasks spawned by a subsystem finishing is no concern of the overseer.
+	#[test]
+	fn subsystem_spawns_tasks() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(SpawnsTasks(tx)),
+				Box::new(ConcludesAtOnce),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut done = rx.take(100).collect::<Vec<_>>().fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				done = done => {
+					let mut done = done;
+					done.sort();
+					assert_eq!(done, (0..100).collect::<Vec<_>>());
+				}
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				health = handler.health().fuse() => {
+					assert_eq!(health.unwrap().get(&SubsystemId::Validation), Some(&Health::Healthy));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that a message that can not be sent right away is handed back.
+	#[test]
+	fn try_send_msg_hands_back_the_message() {
+		executor::block_on(async move {
+			let (mut ctx, mut channels) = test_context::<ValidationSubsystemMessage>(SubsystemId::Validation, 0);
+
+			ctx.try_send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).unwrap();
+
+			match ctx.try_send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)) {
+				Err(TrySendError {
+					error: SubsystemError::MailboxFull,
+					msg: AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second),
+				}) => (),
+				res => panic!("Unexpected result {:?}", res),
+			}
+
+			match channels.from_rx.next().await {
+				Some(ToOverseer::SubsystemMessage { msg: AllMessages::Validation(_), .. }) => (),
+				msg => panic!("Unexpected message {:?}", msg),
+			}
+			drop(channels.from_rx);
+
+			match ctx.try_send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)) {
+				Err(TrySendError { error: SubsystemError::ChannelClosed, .. }) => (),
+				res => panic!("Unexpected result {:?}", res),
+			}
+		})
+	}
+
+
+	// Checks that a subsystem can be tested on its own through a `TestContext`.
+	#[cfg(feature = "test-util")]
+	#[test]
+	fn subsystem_runs_in_test_context() {
+		executor::block_on(async move {
+			let (ctx, mut handle) = test_util::TestContext::new(SubsystemId::Validation);
+			let subsystem = ForwardsToCandidateBacking.start(ctx).0.fuse();
+			pin_mut!(subsystem);
+
+			handle.send_to_subsystem(ValidationSubsystemMessage::ValidityAttestation);
+			select! {
+				_ = subsystem => panic!("Subsystem exited early"),
+				msg = handle.expect_msg().fuse() => match msg {
+					AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second) => (),
+					msg => panic!("Unexpected message {:?}", msg),
+				},
+			}
+
+			drop(handle);
+			assert_eq!(subsystem.await, Err(SubsystemError::ChannelClosed));
+		})
+	}
+
+
+	// Checks that the overseer can be driven a step at a time.
+	#[test]
+	fn overseer_steps() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (mut overseer, mut handler) = Overseer::new(
+				Box::new(ForwardsToCandidateBacking),
+				Box::new(CountsToHundred(Arc::new(AtomicUsize::new(0)))),
+				spawner,
+			).unwrap();
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			// Subscribing.
+			assert!(overseer.step().await.is_none());
+			assert!(events.try_recv().unwrap_err().is_empty());
+
+			// Dispatching the message from the outside world.
+			assert!(overseer.step().await.is_none());
+			assert_eq!(events.try_recv().unwrap(), OverseerEvent::MessageDispatched {
+				id: MessageId(0),
+				causation_id: None,
+				from: None,
+				to: SubsystemId::Validation,
+			});
+			assert!(events.try_recv().unwrap_err().is_empty());
+
+			// Dispatching the message it has caused.
+			assert!(overseer.step().await.is_none());
+			assert_eq!(events.try_recv().unwrap(), OverseerEvent::MessageDispatched {
+				id: MessageId(1),
+				causation_id: Some(MessageId(0)),
+				from: Some(SubsystemId::Validation),
+				to: SubsystemId::CandidateBacking,
+			});
+
+			handler.stop().await.unwrap();
+			assert!(overseer.step().await.unwrap().is_ok());
+		})
+	}
+
+
+	// Checks that a subsystem stops waiting for an overseer that does not answer its spawn request.
+	#[test]
+	fn spawn_timeout_gives_up() {
+		executor::block_on(async move {
+			let (mut ctx, mut channels) = test_context::<ValidationSubsystemMessage>(SubsystemId::Validation, 64);
+
+			let res = ctx.spawn_timeout(Box::pin(async {}), Duration::from_millis(50)).await;
+			assert_eq!(res, Err(SubsystemError::Timeout));
+
+			let answer = async {
+				for _ in 0..2 {
+					match channels.from_rx.next().await {
+						Some(ToOverseer::SpawnJob { res, .. }) => { let _ = res.send(Ok(())); }
+						msg => panic!("Unexpected message {:?}", msg),
+					}
+				}
+			};
+			let (res, _) = future::join(
+				ctx.spawn_timeout(Box::pin(async {}), Duration::from_secs(10)),
+				answer,
+			).await;
+			assert_eq!(res, Ok(()));
+		})
+	}
+
+
+	// Checks that stopping the overseer cancels what the subsystems are waiting for.
+	#[test]
+	fn overseer_stop_cancels_subsystems() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(RacesCancellation(tx)),
+				Box::new(CountsToHundred(Arc::new(AtomicUsize::new(0)))),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+			assert_eq!(rx.next().await, Some(()));
+		})
+	}
+
+
+	// Checks that the other subsystems are restarted along with a failed one under `OneForAll`.
+	#[test]
+	fn overseer_restarts_all_for_one() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Restart,
+				supervision: SupervisionStrategy::OneForAll,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(FailsOnFirstStart(Arc::new(AtomicUsize::new(0)), s1_tx)),
+				Box::new(ReportsIdOnStart(s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut starts = Vec::new();
+			let mut backing_starts = 0;
+			while starts.len() < 2 || backing_starts < 2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					s = s1_rx.next() => starts.push(s.unwrap()),
+					id = s2_rx.next() => {
+						assert_eq!(id, Some(SubsystemId::CandidateBacking));
+						backing_starts += 1;
+					}
+				}
+			}
+			assert_eq!(starts, vec![1, 2]);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+			assert!(s2_rx.next().await.is_none());
+		})
+	}
+
+
+	// Checks that the watchdog notices an overseer that is not making progress,
+	// but not one that is merely idle or yet to be run.
+	#[test]
+	fn watchdog_notices_stalls() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (s2_tx, _s2_rx) = mpsc::unbounded();
+			let (go_tx, go_rx) = oneshot::channel();
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 0,
+				mailbox_policy: MailboxPolicy::Wait,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(GatedReceiver(Some(go_rx), s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer = overseer.with_watchdog(Duration::from_millis(50), move || {
+				let _ = tx.unbounded_send(());
+			});
+
+			// The overseer is not being run yet.
+			Delay::new(Duration::from_millis(150)).await;
+			assert!(rx.try_recv().unwrap_err().is_empty());
+
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut idle = Delay::new(Duration::from_millis(300)).fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				_ = idle => (),
+			}
+			assert!(rx.try_recv().unwrap_err().is_empty());
+
+			// The overseer waits for room in the channel until the gate is opened.
+			for _ in 0..5 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				stall = rx.next() => assert_eq!(stall, Some(())),
+			}
+			go_tx.send(()).unwrap();
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that the watchdog stops an overseer that has stalled when configured to.
+	#[test]
+	fn watchdog_stops_stalled_overseer() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (_go_tx, go_rx) = oneshot::channel();
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 0,
+				mailbox_policy: MailboxPolicy::Wait,
+				stop_on_stall: true,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(GatedReceiver(Some(go_rx), mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer = overseer.with_watchdog(Duration::from_millis(50), || ());
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			// The overseer waits for room in the channel that is never made.
+			for _ in 0..5 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::WatchdogStall);
+		})
+	}
+
+
+	// Checks that a subsystem with a full channel does not hold up the messages
+	// to the others until its overflow is full too.
+	#[test]
+	fn overseer_overflows_full_channels() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (s2_tx, mut s2_rx) = mpsc::unbounded();
+			let (go_tx, go_rx) = oneshot::channel();
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 4,
+				mailbox_policy: MailboxPolicy::Wait,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(GatedReceiver(Some(go_rx), s2_tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			let backing_msg = || AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second);
+			for _ in 0..5 {
+				handler.send_msg(backing_msg()).await.unwrap();
+			}
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			for _ in 0..5 {
+				handler.send_msg(backing_msg()).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				s1 = s1_rx.next() => assert_eq!(s1, Some(0)),
+			}
+
+			go_tx.send(()).unwrap();
+			let mut received = 0;
+			while received < 10 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					_ = s2_rx.next() => received += 1,
+				}
+			}
+
+			let mut overflows = 0;
+			while let Ok(event) = events.try_recv() {
+				if event == OverseerEvent::MailboxOverflow(SubsystemId::CandidateBacking) {
+					overflows += 1;
+				}
+			}
+			assert!(overflows > 0);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that by default a subsystem with its mailbox full does not hold up the others,
+	// the messages to it being handed over to the dead letters as soon as the overflow is full.
+	#[test]
+	fn overseer_does_not_wait_for_full_mailbox() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (dead_tx, mut dead_rx) = mpsc::channel(64);
+			let (_go_tx, go_rx) = oneshot::channel();
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 1,
+				dead_letters: Some(dead_tx),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(GatedReceiver(Some(go_rx), mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			for _ in 0..5 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				s1 = s1_rx.next() => assert_eq!(s1, Some(0)),
+			}
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				letter = dead_rx.next() => assert!(matches!(letter, Some((SubsystemId::CandidateBacking, _)))),
+			}
+
+			let mut overflowed = false;
+			loop {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => match event {
+						Some(OverseerEvent::MailboxOverflow(SubsystemId::CandidateBacking)) => overflowed = true,
+						Some(OverseerEvent::MessageUndeliverable { to: SubsystemId::CandidateBacking, .. }) => break,
+						Some(_) => (),
+						None => panic!("Events stream ended"),
+					},
+				}
+			}
+			assert!(overflowed);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that stopping does not wait for a subsystem that never concludes past the timeout.
+	#[test]
+	fn overseer_stops_with_timeout() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(NeverConcludes),
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				spawner,
+			).unwrap();
+			let (res, report) = future::join(
+				overseer.run(),
+				handler.stop_with_timeout(Duration::from_millis(100)),
+			).await;
+
+			assert_eq!(res.reason, ShutdownReason::ExternalStop);
+			assert_eq!(report.unwrap(), StopReport {
+				clean: vec![SubsystemId::CandidateBacking],
+				forced: vec![SubsystemId::Validation],
+			});
+		})
+	}
+
+
+	// Checks that the state of the subsystems can be inspected.
+	#[test]
+	fn overseer_can_be_inspected() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (go_tx, go_rx) = oneshot::channel();
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Restart,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(FailsOnFirstStart(Arc::new(AtomicUsize::new(0)), s1_tx)),
+				Box::new(GatedReceiver(Some(go_rx), mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut starts = Vec::new();
+			while starts.len() < 2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					s = s1_rx.next() => starts.push(s.unwrap()),
+				}
+			}
+
+			for _ in 0..3 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				info = handler.inspect().fuse() => {
+					let info = info.unwrap();
+					assert_eq!(info[0], SubsystemInfo {
+						id: SubsystemId::Validation,
+						running: true,
+						mailbox_depth: 0,
+						restarts: 1,
+						since_last_dispatch: None,
+					});
+					assert_eq!(info[1].id, SubsystemId::CandidateBacking);
+					assert!(info[1].running);
+					assert_eq!(info[1].mailbox_depth, 3);
+					assert_eq!(info[1].restarts, 0);
+					assert!(info[1].since_last_dispatch.is_some());
+				}
+			}
+
+			drop(go_tx);
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the messages to a paused subsystem are delivered in order once it is resumed.
+	#[test]
+	fn overseer_pauses_subsystems() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(ReportsMessageIds(tx)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			handler.pause_subsystem(SubsystemId::CandidateBacking).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				info = handler.inspect().fuse() => assert_eq!(info.unwrap()[1].mailbox_depth, 3),
+			}
+			assert!(rx.try_recv().unwrap_err().is_empty());
+
+			handler.resume_subsystem(SubsystemId::CandidateBacking).await.unwrap();
+			let mut received = Vec::new();
+			while received.len() < 3 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					id = rx.next() => received.push(id.unwrap()),
+				}
+			}
+			assert_eq!(received, vec![Some(MessageId(0)), Some(MessageId(1)), Some(MessageId(2))]);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a message to a paused subsystem with its overflow full is handed over to the dead letters.
+	#[test]
+	fn paused_subsystem_with_full_overflow_hands_over_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (dead_tx, mut dead_rx) = mpsc::channel(64);
+			let config = OverseerConfig {
+				overflow_capacity: 1,
+				dead_letters: Some(dead_tx),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(ReportsMessageIds(mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.pause_subsystem(SubsystemId::CandidateBacking).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				letter = dead_rx.next() => match letter {
+					Some((SubsystemId::CandidateBacking, AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher))) => (),
+					letter => panic!("Unexpected dead letter {:?}", letter),
+				},
+			}
+
+			loop {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => match event {
+						Some(OverseerEvent::MessageUndeliverable { from: None, to: SubsystemId::CandidateBacking }) => break,
+						Some(_) => (),
+						None => panic!("Events stream ended"),
+					},
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that the messages from one subsystem to another are received in the order they are sent.
+	#[test]
+	fn overseer_preserves_order_per_sender() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 4,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(SendsRunsOfSeconds),
+				Box::new(ReportsSeconds(tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut expected = Vec::new();
+			for i in 0..100 {
+				expected.extend(vec![true; i % 3 + 1]);
+				expected.push(false);
+			}
+
+			let mut received = Vec::new();
+			while received.len() < expected.len() {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					second = rx.next() => received.push(second.unwrap()),
+					_ = handler.send_msg(
+						AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)
+					).fuse() => (),
+				}
+			}
+			assert_eq!(received, expected);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the stream of messages yields them and ends once the channel is dropped.
+	#[test]
+	fn recv_stream_ends_when_channel_closed() {
+		executor::block_on(async move {
+			let (mut ctx, channels) = test_context::<CandidateBackingSubsystemMessage>(
+				SubsystemId::CandidateBacking,
+				CHANNEL_CAPACITY,
+			);
+
+			for _ in 0..3 {
+				channels.to_tx.unbounded_send(Envelope {
+					id: None,
+					msg: FromOverseer::Communication { msg: CandidateBackingSubsystemMessage::Second },
+					span: MessageSpan::none(),
+				}).unwrap();
+			}
+			channels.signals_tx.unbounded_send(OverseerSignal::StartWork).unwrap();
+			drop(channels.to_tx);
+
+			let received: Vec<_> = ctx.recv_stream().collect().await;
+			assert_eq!(received.len(), 4);
+			assert!(matches!(received[0], FromOverseer::Signal(OverseerSignal::StartWork)));
+			assert!(received[1..].iter().all(|msg| matches!(
+				msg,
+				FromOverseer::Communication { msg: CandidateBackingSubsystemMessage::Second },
+			)));
+		})
+	}
+
+
+	// Checks that receiving with a timeout gives up when no message arrives.
+	#[test]
+	fn recv_timeout_fires_without_messages() {
+		executor::block_on(async move {
+			let (mut ctx, channels) = test_context::<CandidateBackingSubsystemMessage>(
+				SubsystemId::CandidateBacking,
+				CHANNEL_CAPACITY,
+			);
+
+			let res = ctx.recv_timeout(Duration::from_millis(50)).await;
+			assert_eq!(res.err(), Some(SubsystemError::Timeout));
+
+			channels.to_tx.unbounded_send(Envelope {
+				id: None,
+				msg: FromOverseer::Communication { msg: CandidateBackingSubsystemMessage::Second },
+				span: MessageSpan::none(),
+			}).unwrap();
+			assert!(matches!(
+				ctx.recv_timeout(Duration::from_secs(5)).await,
+				Ok(FromOverseer::Communication { msg: CandidateBackingSubsystemMessage::Second }),
+			));
+
+			drop(channels.to_tx);
+			let res = ctx.recv_timeout(Duration::from_secs(5)).await;
+			assert_eq!(res.err(), Some(SubsystemError::ChannelClosed));
+		})
+	}
+
+
+	// Checks that the overseer can be spawned and selected over as a future of its own.
+	#[test]
+	fn overseer_is_a_future() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(TestSubsystem2(mpsc::channel(64).0)),
+				spawner.clone(),
+			).unwrap();
+			let overseer = spawner.spawn_with_handle(overseer).unwrap();
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer.await.reason, ShutdownReason::ExternalStop);
+
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(TestSubsystem2(mpsc::channel(64).0)),
+				spawner,
+			).unwrap();
+			pin_mut!(overseer);
+
+			select! {
+				_ = overseer => panic!("Overseer exited early"),
+				res = handler.list_subsystems().fuse() => assert_eq!(res.unwrap().len(), 2),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the state of the overseer can be dumped for the logs.
+	#[test]
+	fn overseer_state_can_be_dumped() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let config = OverseerConfig {
+				dependencies: vec![(SubsystemId::CandidateBacking, SubsystemId::Validation)],
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(TestSubsystem2(mpsc::channel(64).0)),
+				spawner,
+				config,
+			).unwrap();
+
+			let dump = overseer.debug_dump();
+			assert!(dump.starts_with("overseer: 0 queued messages, 0 dead letters\n"));
+			assert!(dump.contains("Validation: running, 0 restarts, 0 in mailbox, 0 overflowed\n"));
+			assert!(dump.contains("CandidateBacking: running, 0 restarts, 0 in mailbox, 0 overflowed\n  depends on Validation\n"));
+
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			handler.pause_subsystem(SubsystemId::CandidateBacking).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				dump = handler.debug_dump().fuse() => {
+					assert!(dump.unwrap().contains("CandidateBacking: running, 0 restarts, 0 in mailbox, 0 overflowed, paused\n"));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that an overseer being run can not be inspected directly, as its state is elsewhere.
+	#[test]
+	#[should_panic(expected = "is being run")]
+	fn overseer_not_inspected_while_running() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (mut overseer, _handler) = Overseer::new(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(TestSubsystem2(mpsc::channel(64).0)),
+				spawner,
+			).unwrap();
+
+			assert!(futures::poll!(&mut overseer).is_pending());
+			overseer.debug_dump();
+		})
+	}
+
+	// Checks that the dead letters are closed once the overseer has finished,
+	// even while what is left of it is still around.
+	#[test]
+	fn overseer_closes_dead_letters_once_finished() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (dead_tx, mut dead_rx) = mpsc::channel(64);
+			let config = OverseerConfig {
+				dead_letters: Some(dead_tx),
+				..Default::default()
+			};
+			let (mut overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+
+			handler.stop().await.unwrap();
+			assert_eq!((&mut overseer).await.reason, ShutdownReason::ExternalStop);
+			select! {
+				letter = dead_rx.next() => assert!(letter.is_none()),
+				_ = Delay::new(Duration::from_secs(1)).fuse() => panic!("Dead letters still open"),
+			}
+		})
+	}
+
+	// Checks that a subsystem that concludes is removed while the others keep running.
+	#[test]
+	fn subsystem_can_conclude() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, mut s1_rx) = mpsc::channel(64);
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(ConcludesAfter(3)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			for _ in 0..3 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::CandidateBacking).fuse() => assert_eq!(res, Ok(())),
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				ids = handler.list_subsystems().fuse() => assert_eq!(ids.unwrap(), vec![SubsystemId::Validation]),
+			}
+
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				i = s1_rx.next() => assert_eq!(i, Some(0)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the messages to a subsystem that has concluded are dropped, and that
+	// its exit is told apart from the one of a subsystem that has not concluded.
+	#[test]
+	fn concluded_subsystem_is_told_apart() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (concluded_tx, mut concluded_rx) = mpsc::unbounded();
+			let (exit_tx, exit_rx) = oneshot::channel::<()>();
+			let exit_rx = exit_rx.shared();
+			let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				ctx.recv().await?;
+				Ok(())
+			});
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let concluded_tx = concluded_tx.clone();
+				let exit_rx = exit_rx.clone();
+				async move {
+					ctx.recv().await?;
+					let res = ctx.conclude();
+					concluded_tx.unbounded_send(()).unwrap();
+					let _ = exit_rx.await;
+					res
+				}
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				events = handler.events().fuse() => events.unwrap(),
+			};
+
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				_ = concluded_rx.next() => (),
+			}
+
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			// The concluded subsystem finishes once the message to it has been dropped.
+			let mut exit_tx = Some(exit_tx);
+			let mut seen = Vec::new();
+			while seen.len() < 2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					event = events.next() => match event {
+						Some(OverseerEvent::MessageUndeliverable { to, .. }) => {
+							assert_eq!(to, SubsystemId::CandidateBacking);
+							if let Some(exit_tx) = exit_tx.take() {
+								let _ = exit_tx.send(());
+							}
+						}
+						Some(event @ OverseerEvent::SubsystemExited(_)) | Some(event @ OverseerEvent::SubsystemStopped(_)) => seen.push(event),
+						_ => (),
+					},
+				}
+			}
+
+			assert!(seen.contains(&OverseerEvent::SubsystemExited(SubsystemId::Validation)));
+			assert!(seen.contains(&OverseerEvent::SubsystemStopped(SubsystemId::CandidateBacking)));
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a subsystem in another process gets its messages, that its replies
+	// are sent on and that it fails once the other process has gone away.
+	#[cfg(feature = "proxy")]
+	#[test]
+	fn proxy_subsystem_bridges_to_other_process() {
+		use futures::stream::TryStreamExt;
+		use proxy::{read_frame, write_frame, ProxySubsystem};
+
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (to_child_tx, to_child_rx) = mpsc::unbounded();
+			let (from_child_tx, from_child_rx) = mpsc::unbounded();
+
+			// The other process answers two messages and crashes.
+			spawner.spawn(async move {
+				let mut r = to_child_rx.into_async_read();
+				let mut w = ChannelWriter(from_child_tx);
+				let mut answered = 0;
+
+				while answered < 2 {
+					let msg = read_frame::<_, FromOverseer<ValidationSubsystemMessage>>(&mut r).await.unwrap();
+					if let FromOverseer::Communication { .. } = msg {
+						write_frame(
+							&mut w,
+							&AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second),
+						).await.unwrap();
+						answered += 1;
+					}
+				}
+			}).unwrap();
+
+			let mut transport = Some((from_child_rx.into_async_read(), ChannelWriter(to_child_tx)));
+			let proxy = ProxySubsystem::new(move || {
+				transport.take().ok_or_else(|| std::io::ErrorKind::NotConnected.into())
+			});
+
+			let (tx, mut rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(proxy),
+				Box::new(ReportsSeconds(tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			for _ in 0..2 {
+				handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			}
+
+			for _ in 0..2 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					second = rx.next() => assert_eq!(second, Some(true)),
+				}
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => {
+					assert_eq!(res, Err(SubsystemError::ChannelClosed));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that a closure can be overseen as a subsystem and is called again on restarts.
+	#[test]
+	fn closure_can_be_a_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let mut starts = 0;
+			let validation = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				starts += 1;
+				let tx = tx.clone();
+				let start = starts;
+
+				async move {
+					if let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(start).unwrap();
+					}
+
+					Err(SubsystemError::SubsystemStopped(SubsystemId::Validation))
+				}
+			});
+
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Restart,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+
+			for expected in 1..=2 {
+				handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					start = rx.next() => assert_eq!(start, Some(expected)),
+				}
+
+				// A message sent before the closure has been called again would not be delivered.
+				loop {
+					select! {
+						_ = overseer_fut => panic!("Overseer exited early"),
+						event = events.next() => match event {
+							Some(OverseerEvent::SubsystemStarted(SubsystemId::Validation)) => break,
+							Some(_) => (),
+							None => panic!("Overseer has stopped sending events"),
+						},
+					}
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the errors of channels convert into a `SubsystemError`, which converts
+	// into a boxed error.
+	#[test]
+	fn channel_errors_convert() {
+		fn send(tx: &mpsc::UnboundedSender<()>) -> Result<(), Box<dyn std::error::Error>> {
+			let res: SubsystemResult<()> = tx.unbounded_send(()).map_err(Into::into);
+			Ok(res?)
+		}
+
+		let (mut tx, rx) = mpsc::channel(0);
+		tx.try_send(()).unwrap();
+		assert_eq!(SubsystemError::from(tx.try_send(()).unwrap_err()), SubsystemError::MailboxFull);
+		drop(rx);
+		assert_eq!(SubsystemError::from(tx.try_send(()).unwrap_err()), SubsystemError::ChannelClosed);
+
+		let (tx, rx) = mpsc::unbounded();
+		assert!(send(&tx).is_ok());
+		drop(rx);
+		assert_eq!(send(&tx).unwrap_err().to_string(), "channel closed");
+	}
+
+
+	// Checks that a message to a full channel is retried until the subsystem catches up,
+	// and that it is handed over to the dead letters once the retries are exhausted.
+	#[test]
+	fn overseer_retries_delivery_to_full_channel() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let config = |delivery_retries, dead_letters| OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 0,
+				delivery_retries,
+				mailbox_policy: MailboxPolicy::DeadLetter,
+				dead_letters: Some(dead_letters),
+				..Default::default()
+			};
+			let second = || AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second);
+
+			// The subsystem stops reading for a while, but within the retries.
+			let (go_tx, go_rx) = oneshot::channel();
+			let (tx, mut rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(GatedReceiver(Some(go_rx), tx)),
+				spawner.clone(),
+				config(10_000_000, mpsc::channel(8).0),
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			for _ in 0..5 {
+				handler.send_msg(second()).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				_ = Delay::new(Duration::from_millis(20)).fuse() => go_tx.send(()).unwrap(),
+			}
+
+			for _ in 0..5 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					received = rx.next() => assert_eq!(received, Some(())),
+				}
+			}
+			assert_eq!(handler.metrics().undeliverable, 0);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			// The subsystem does not read at all.
+			let (go_tx, go_rx) = oneshot::channel();
+			let (tx, _rx) = mpsc::unbounded();
+			let (dead_letters, mut dead_letters_rx) = mpsc::channel(8);
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(mpsc::channel(64).0)),
+				Box::new(GatedReceiver(Some(go_rx), tx)),
+				spawner,
+				config(3, dead_letters),
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			for _ in 0..3 {
+				handler.send_msg(second()).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				letter = dead_letters_rx.next() => {
+					assert!(matches!(
+						letter,
+						Some((SubsystemId::CandidateBacking, AllMessages::CandidateBacking(_))),
+					));
+				}
+			}
+			assert_eq!(handler.metrics().undeliverable, 1);
+
+			drop(go_tx);
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	#[derive(Clone)]
+	struct CountingSpawner(ThreadPool, Arc<AtomicUsize>);
+
+	impl Spawn for CountingSpawner {
+		fn spawn_obj(&self, future: FutureObj<'static, ()>) -> Result<(), SpawnError> {
+			self.1.fetch_add(1, Ordering::SeqCst);
+			self.0.spawn_obj(future)
+		}
+	}
+
+	// Checks that the tasks spawned with the spawner of a context end up on the executor of the overseer.
+	#[test]
+	fn context_exposes_spawner() {
+		let spawned = Arc::new(AtomicUsize::new(0));
+		let spawner = CountingSpawner(executor::ThreadPool::new().unwrap(), spawned.clone());
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let validation = FnSubsystem::new(move |ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					let spawner = ctx.spawner();
+					ctx.spawner().spawn(async move {
+						spawner.spawn(async move {
+							tx.unbounded_send(()).unwrap();
+						}).unwrap();
+					})?;
+
+					ctx.conclude()
+				}
+			});
+
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				reported = rx.next() => assert_eq!(reported, Some(())),
+			}
+
+			// Both subsystems and the two tasks.
+			assert_eq!(spawned.load(Ordering::SeqCst), 4);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the queued messages of one priority are dispatched to the heavier subsystem first,
+	// while the messages to each subsystem keep their order.
+	#[test]
+	fn overseer_dispatches_by_weight() {
+		let spawner = executor::ThreadPool::new().unwrap();
+		let (tx, _rx) = mpsc::unbounded();
+		let mut config = OverseerConfig::default();
+		config.weights.insert(SubsystemId::CandidateBacking, 2);
+		let (mut overseer, _handler) = Overseer::with_config(
+			Box::new(ReportsIdOnStart(tx.clone())),
+			Box::new(ReportsIdOnStart(tx)),
+			spawner,
+			config,
+		).unwrap();
+
+		let messages = vec![
+			AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation),
+			AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second),
+			AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation),
+			AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher),
+		];
+		for msg in messages {
+			overseer.queued[Priority::Normal as usize].push_back(QueuedMessage {
+				from: SubsystemId::Validation,
+				causation_id: None,
+				msg,
+				deadline: None,
+				span: MessageSpan::none(),
+			});
+		}
+
+		overseer.order_queued_by_weight();
+		let mut order = Vec::new();
+		while let Some(queued) = overseer.next_queued() {
+			order.push(match queued.msg {
+				AllMessages::Validation(_) => "validation",
+				AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second) => "second",
+				AllMessages::CandidateBacking(_) => "watcher",
+			});
+		}
+
+		assert_eq!(order, vec!["second", "watcher", "validation", "validation"]);
+	}
+
+
+	// Checks that a dependency is only concluded once its dependent has concluded,
+	// and that a subsystem not concluding in time is reported.
+	#[test]
+	fn overseer_concludes_dependents_first() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, rx) = mpsc::unbounded();
+			let validation_tx = tx.clone();
+			let validation = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = validation_tx.clone();
+
+				async move {
+					while let Ok(msg) = ctx.recv().await {
+						if let FromOverseer::Signal(OverseerSignal::Conclude) = msg {
+							Delay::new(Duration::from_millis(50)).await;
+							tx.unbounded_send(SubsystemId::Validation).unwrap();
+							break;
+						}
+					}
+
+					ctx.conclude()
+				}
+			});
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let Ok(msg) = ctx.recv().await {
+						if let FromOverseer::Signal(OverseerSignal::Conclude) = msg {
+							tx.unbounded_send(SubsystemId::CandidateBacking).unwrap();
+							future::pending::<()>().await;
+						}
+					}
+
+					Ok(())
+				}
+			});
+
+			let config = OverseerConfig {
+				dependencies: vec![(SubsystemId::Validation, SubsystemId::CandidateBacking)],
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+				config,
+			).unwrap();
+			let (report, res) = futures::join!(handler.conclude_all(Duration::from_millis(500)), overseer);
+
+			assert_eq!(report.unwrap(), StopReport {
+				clean: vec![SubsystemId::Validation],
+				forced: vec![SubsystemId::CandidateBacking],
+			});
+			assert_eq!(res.reason, ShutdownReason::ExternalStop);
+			assert_eq!(
+				rx.collect::<Vec<_>>().await,
+				vec![SubsystemId::Validation, SubsystemId::CandidateBacking],
+			);
+		})
+	}
+
+
+	// Checks that a subsystem asking for its peers while being concluded gets an answer.
+	#[test]
+	fn overseer_answers_peers_while_concluding() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, rx) = mpsc::unbounded();
+			let validation = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let Ok(msg) = ctx.recv().await {
+						if let FromOverseer::Signal(OverseerSignal::Conclude) = msg {
+							tx.unbounded_send(ctx.peers().await).unwrap();
+							break;
+						}
+					}
+
+					ctx.conclude()
+				}
+			});
+
+			let candidate_backing = FnSubsystem::new(|mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| async move {
+				while let Ok(msg) = ctx.recv().await {
+					if let FromOverseer::Signal(OverseerSignal::Conclude) = msg {
+						break;
+					}
+				}
+
+				ctx.conclude()
+			});
+
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+			).unwrap();
+			let (report, res) = futures::join!(handler.conclude_all(Duration::from_millis(500)), overseer);
+
+			assert!(report.unwrap().forced.is_empty());
+			assert_eq!(res.reason, ShutdownReason::ExternalStop);
+
+			let answers = rx.collect::<Vec<_>>().await;
+			assert_eq!(answers.len(), 1);
+			assert!(!answers[0].as_ref().unwrap().contains(&SubsystemId::Validation));
+		})
+	}
+
+
+	// Checks that a task spawned by a subsystem is cancelled when the overseer has been dropped.
+	#[test]
+	fn subsystem_task_cancelled_when_overseer_gone() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let validation = FnSubsystem::new(move |ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					tx.unbounded_send(ctx.is_overseer_alive()).unwrap();
+
+					let gone = ctx.cancelled();
+					let task_tx = tx.clone();
+					ctx.spawn_task(async move {
+						gone.await;
+						task_tx.unbounded_send(false).unwrap();
+					})?;
+
+					future::pending().await
+				}
+			});
+
+			let (overseer, _handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+			).unwrap();
+
+			assert_eq!(rx.next().await, Some(true));
+			drop(overseer);
+			assert_eq!(rx.next().await, Some(false));
+		})
+	}
+
+
+	// Checks that the kind of a message is the name of its variant.
+	#[test]
+	fn message_kind_is_variant_name() {
+		assert_eq!(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation).kind(), "ValidityAttestation");
+		assert_eq!(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second).kind(), "Second");
+		assert_eq!(CandidateBackingSubsystemMessage::RegisterBackingWatcher.kind(), "RegisterBackingWatcher");
+	}
+
+
+	// Checks that a handle of a subsystem from before it has been restarted is ignored
+	// when it finishes, rather than taken for the running subsystem failing.
+	#[test]
+	fn overseer_ignores_stale_handles() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let validation = FnSubsystem::new(|_: SubsystemContext<ValidationSubsystemMessage>| {
+				future::pending::<SubsystemResult<()>>()
+			});
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Panic,
+				..Default::default()
+			};
+			let (mut overseer, _handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+
+			overseer.running_subsystems.push(
+				future::ready((SubsystemId::Validation, 0, Err(SubsystemError::ChannelClosed))).boxed(),
+			);
+
+			assert!(overseer.step().await.is_none());
+			assert!(overseer.running_subsystem_ids().contains(&SubsystemId::Validation));
+		})
+	}
+
+
+	// Checks that a subsystem can be spawned through the handler in the place of one that has concluded,
+	// but not in the place of one that is running.
+	#[test]
+	fn subsystem_can_be_spawned_through_handler() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let concluding = FnSubsystem::new(|ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				ctx.conclude()
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(concluding),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Ok(())),
+			}
+
+			let (tx, mut rx) = mpsc::channel(8);
+			let validation = Box::new(TestSubsystem1(tx));
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				id = handler.spawn_subsystem(AnySubsystem::Validation(validation)).fuse() => {
+					assert_eq!(id, Ok(SubsystemId::Validation));
+				}
+			}
+
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				received = rx.next() => assert_eq!(received, Some(0)),
+			}
+
+			let (tx, _rx) = mpsc::unbounded();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				id = handler.spawn_subsystem(AnySubsystem::Validation(Box::new(ReportsIdOnStart(tx)))).fuse() => {
+					assert_eq!(id, Err(SubsystemError::AlreadyRunning));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that the handler is handed back its message once the channel to the overseer is full.
+	#[test]
+	fn handler_try_send_msg_hands_back_the_message() {
+		let spawner = executor::ThreadPool::new().unwrap();
+		let (tx, _rx) = mpsc::unbounded();
+		let config = OverseerConfig {
+			events_capacity: 0,
+			..Default::default()
+		};
+		let (_overseer, mut handler) = Overseer::with_config(
+			Box::new(ReportsIdOnStart(tx.clone())),
+			Box::new(ReportsIdOnStart(tx)),
+			spawner,
+			config,
+		).unwrap();
+
+		let msg = || AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second);
+		assert!(handler.try_send_msg(msg()).is_ok());
+
+		match handler.try_send_msg(msg()) {
+			Err(TrySendError { error, msg: AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second) }) => {
+				assert_eq!(error, SubsystemError::MailboxFull);
+			}
+			res => panic!("Unexpected {:?}", res),
+		}
+	}
+
+
+	// Checks that a subsystem returning, failing and panicking are told apart.
+	#[test]
+	fn overseer_tells_exits_apart() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(FnSubsystem::new(|_: SubsystemContext<ValidationSubsystemMessage>| async { Ok(()) })),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Ok(())),
+			}
+
+			let failing = FnSubsystem::new(|_: SubsystemContext<ValidationSubsystemMessage>| async {
+				Err(SubsystemError::ChannelClosed)
+			});
+			let panicking = FnSubsystem::new(|_: SubsystemContext<ValidationSubsystemMessage>| async {
+				panic!("Subsystem panicked on purpose")
+			});
+			let exits = vec![
+				(AnySubsystem::Validation(Box::new(failing)), SubsystemError::ChannelClosed),
+				(AnySubsystem::Validation(Box::new(panicking)), SubsystemError::SubsystemPanicked),
+			];
+
+			for (s, expected) in exits {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					res = handler.spawn_subsystem(s).fuse() => assert_eq!(res, Ok(SubsystemId::Validation)),
+				}
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Err(expected)),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that a subsystem is told of the other running subsystems, but not of itself.
+	#[test]
+	fn subsystem_lists_peers() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let validation = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					tx.unbounded_send(ctx.peers().await?).unwrap();
+
+					ctx.conclude()
+				}
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(ReportsSeconds(mpsc::unbounded().0)),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				peers = rx.next() => assert_eq!(peers, Some(vec![SubsystemId::CandidateBacking])),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+
+	// Checks that a subsystem whose mailbox grows to its high water mark is reported once as lagging.
+	#[test]
+	fn overseer_reports_lagging_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _s1_rx) = mpsc::channel(64);
+			let config = OverseerConfig {
+				validation_channels: ChannelConfig {
+					high_water_mark: Some(3),
+					..Default::default()
+				},
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(AwaitsSignal(s1_tx)),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			for _ in 0..5 {
+				handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			}
+
+			// The mailbox grows past the mark with the third message, and the
+			// event follows its dispatch.
+			let mut lagging = Vec::new();
+			for _ in 0..5 {
+				loop {
+					select! {
+						_ = overseer_fut => panic!("Overseer exited early"),
+						event = events.next() => match event.unwrap() {
+							OverseerEvent::MessageDispatched { .. } => break,
+							event => lagging.push(event),
+						},
+					}
+				}
+			}
+
+			assert_eq!(lagging, vec![OverseerEvent::SubsystemLagging(SubsystemId::Validation, 3)]);
+			assert_eq!(handler.metrics().subsystems[&SubsystemId::Validation].lagging, 1);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a subsystem times out on a `TestClock` as soon as it is advanced, without waiting.
+	#[cfg(feature = "test-util")]
+	#[test]
+	fn subsystem_times_out_on_test_clock() {
+		executor::block_on(async move {
+			let clock = test_util::TestClock::new();
+			let (mut ctx, _handle) = test_util::TestContext::<ValidationSubsystemMessage>::with_clock(
+				SubsystemId::Validation,
+				Arc::new(clock.clone()),
+			);
+			let started = ctx.clock().now();
+
+			let recv = ctx.recv_timeout(Duration::from_secs(3600));
+			pin_mut!(recv);
+			assert!(futures::poll!(&mut recv).is_pending());
+
+			clock.advance(Duration::from_secs(1800));
+			assert!(futures::poll!(&mut recv).is_pending());
+
+			clock.advance(Duration::from_secs(1800));
+			assert!(matches!(recv.await, Err(SubsystemError::Timeout)));
+			assert_eq!(clock.now() - started, Duration::from_secs(3600));
+		})
+	}
+
+	// Checks that the time since the last dispatch to a subsystem is told by the clock of the overseer.
+	#[cfg(feature = "test-util")]
+	#[test]
+	fn overseer_inspects_on_its_clock() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(()).unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let clock = test_util::TestClock::new();
+			let config = OverseerConfig {
+				clock: Some(Arc::new(clock.clone())),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(candidate_backing),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				received = rx.next() => assert!(received.is_some()),
+			}
+
+			clock.advance(Duration::from_secs(3600));
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				info = handler.inspect().fuse() => {
+					let info = info.unwrap();
+					assert_eq!(info[1].since_last_dispatch, Some(Duration::from_secs(3600)));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that middleware runs in order, and can drop and transform messages.
+	#[test]
+	fn overseer_passes_messages_through_middleware() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let FromOverseer::Communication { msg } = ctx.recv().await? {
+						tx.unbounded_send(msg.kind()).unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let (seen_tx, seen_rx) = std::sync::mpsc::channel();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ReportsIdOnStart(mpsc::unbounded().0)),
+				Box::new(candidate_backing),
+				spawner,
+			).unwrap();
+			let overseer = overseer
+				.with_middleware(|_, _, msg| match msg {
+					AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second) => None,
+					msg => Some(msg),
+				})
+				.with_middleware(move |from, to, msg| {
+					seen_tx.send((from, to, msg.kind())).unwrap();
+					match msg {
+						AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher) =>
+							Some(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)),
+						msg => Some(msg),
+					}
+				});
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(
+				CandidateBackingSubsystemMessage::RegisterBackingWatcher,
+			)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				kind = rx.next() => assert_eq!(kind, Some("Second")),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			assert_eq!(seen_rx.try_iter().collect::<Vec<_>>(), vec![
+				(None, SubsystemId::CandidateBacking, "RegisterBackingWatcher"),
+			]);
+		})
+	}
+
+	// Checks that a subsystem replaced while messages keep coming hands none of them over to the void.
+	#[test]
+	fn subsystem_is_replaced_without_losing_messages() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let reporting = |name: &'static str| {
+				let tx = tx.clone();
+
+				FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+					let tx = tx.clone();
+
+					async move {
+						while let FromOverseer::Communication { .. } = ctx.recv().await? {
+							Delay::new(Duration::from_millis(10)).await;
+							tx.unbounded_send(name).unwrap();
+						}
+
+						ctx.conclude()
+					}
+				})
+			};
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(reporting("old")),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let msg = || AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation);
+			for _ in 0..3 {
+				handler.send_msg(msg()).await.unwrap();
+			}
+			let (replaced_tx, replaced_rx) = oneshot::channel();
+			let replacement = AnySubsystem::Validation(Box::new(reporting("new")));
+			handler.events_tx.send(Event::ReplaceSubsystem(replacement, replaced_tx)).await.unwrap();
+			for _ in 0..3 {
+				handler.send_msg(msg()).await.unwrap();
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				replaced = replaced_rx.fuse() => assert_eq!(replaced.unwrap(), Ok(SubsystemId::Validation)),
+			}
+
+			let mut received = Vec::new();
+			while received.len() < 6 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					name = rx.next() => received.push(name.unwrap()),
+				}
+			}
+			assert_eq!(received, vec!["old", "old", "old", "new", "new", "new"]);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that replacing a subsystem with a full mailbox does not hold the overseer up,
+	// and that the old subsystem still handles what has been kept for it first.
+	#[test]
+	fn subsystem_with_full_mailbox_is_replaced() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (gate_tx, gate_rx) = oneshot::channel::<()>();
+			let mut gate_rx = Some(gate_rx);
+			let old_tx = tx.clone();
+			let old = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = old_tx.clone();
+				let gate_rx = gate_rx.take();
+
+				async move {
+					if let Some(gate_rx) = gate_rx {
+						let _ = gate_rx.await;
+					}
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send("old").unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let new = FnSubsystem::new(move |mut ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send("new").unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let config = OverseerConfig {
+				validation_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(old),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let msg = || AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation);
+			for _ in 0..5 {
+				handler.send_msg(msg()).await.unwrap();
+			}
+			let (replaced_tx, replaced_rx) = oneshot::channel();
+			let replacement = AnySubsystem::Validation(Box::new(new));
+			handler.events_tx.send(Event::ReplaceSubsystem(replacement, replaced_tx)).await.unwrap();
+
+			let mut timeout = Delay::new(Duration::from_secs(5)).fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				info = handler.inspect().fuse() => assert!(info.unwrap()[0].running),
+				_ = timeout => panic!("Overseer held up by the replacement"),
+			}
+
+			drop(gate_tx);
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				replaced = replaced_rx.fuse() => assert_eq!(replaced.unwrap(), Ok(SubsystemId::Validation)),
+			}
+			handler.send_msg(msg()).await.unwrap();
+
+			let mut received = Vec::new();
+			while received.len() < 6 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					name = rx.next() => received.push(name.unwrap()),
+				}
+			}
+			assert_eq!(received, vec!["old", "old", "old", "old", "old", "new"]);
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a subsystem flooding the overseer is held to its rate limit.
+	#[cfg(feature = "test-util")]
+	#[test]
+	fn overseer_holds_subsystem_to_rate_limit() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				for _ in 0..20 {
+					ctx.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await?;
+				}
+
+				// Stopping waits for the subsystems to conclude on the test clock.
+				while let FromOverseer::Communication { .. } = ctx.recv().await? {}
+
+				ctx.conclude()
+			});
+			let (tx, mut rx) = mpsc::unbounded();
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(()).unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let clock = test_util::TestClock::new();
+			let mut config = OverseerConfig {
+				clock: Some(Arc::new(clock.clone())),
+				..Default::default()
+			};
+			config.rate_limits.insert(SubsystemId::Validation, RateLimit {
+				messages_per_second: 10,
+				burst: 5,
+				policy: RateLimitPolicy::Delay,
+			});
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			// The burst, and then as much as the time passed allows for, up to another burst.
+			for &(advance, expected) in &[(0, 5), (500, 5), (2000, 5), (500, 5)] {
+				clock.advance(Duration::from_millis(advance));
+
+				for _ in 0..expected {
+					select! {
+						_ = overseer_fut => panic!("Overseer exited early"),
+						received = rx.next() => assert!(received.is_some()),
+					}
+				}
+
+				let mut idle = Delay::new(Duration::from_millis(50)).fuse();
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					_ = rx.next() => panic!("More messages delivered than the rate limit allows"),
+					_ = idle => (),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that posting a message does not wait for room in the channel to the overseer.
+	#[test]
+	fn post_msg_does_not_wait() {
+		executor::block_on(async move {
+			let (mut ctx, mut channels) = test_context::<ValidationSubsystemMessage>(SubsystemId::Validation, 0);
+
+			let msg = || AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second);
+			ctx.try_send_msg(msg()).unwrap();
+			assert_eq!(ctx.try_send_msg(msg()).unwrap_err().error, SubsystemError::MailboxFull);
+
+			for _ in 0..100 {
+				ctx.post_msg(msg()).unwrap();
+			}
+
+			for _ in 0..100 {
+				match channels.posts_rx.next().await {
+					Some(ToOverseer::SubsystemMessage { msg: AllMessages::CandidateBacking(_), .. }) => (),
+					msg => panic!("Unexpected message {:?}", msg),
+				}
+			}
+			assert!(channels.from_rx.next().await.is_some());
+
+			drop(channels.posts_rx);
+			assert_eq!(ctx.post_msg(msg()), Err(SubsystemError::ChannelClosed));
+		})
+	}
+
+	// Checks that no more messages are dispatched while the mailboxes hold the whole budget,
+	// and that those held back are dispatched once the mailboxes have drained.
+	#[test]
+	fn overseer_keeps_to_message_budget() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				for _ in 0..10 {
+					ctx.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await?;
+				}
+
+				while let FromOverseer::Communication { .. } = ctx.recv().await? {}
+
+				ctx.conclude()
+			});
+			let (go_tx, go_rx) = oneshot::channel();
+			let mut go_rx = Some(go_rx);
+			let (tx, mut rx) = mpsc::unbounded();
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let go_rx = go_rx.take();
+				let tx = tx.clone();
+
+				async move {
+					if let Some(go_rx) = go_rx {
+						let _ = go_rx.await;
+					}
+
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {
+						tx.unbounded_send(()).unwrap();
+					}
+
+					ctx.conclude()
+				}
+			});
+			let config = OverseerConfig {
+				message_budget: Some(3),
+				messages_per_pass: 1,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+
+			let mut idle = Delay::new(Duration::from_millis(100)).fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				_ = idle => (),
+			}
+			assert_eq!(handler.metrics().subsystems[&SubsystemId::CandidateBacking].mailbox_depth, 3);
+
+			go_tx.send(()).unwrap();
+			for _ in 0..11 {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					received = rx.next() => assert!(received.is_some()),
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that the lifecycle hooks are called around the future of a subsystem,
+	// and that a replaced subsystem is stopped before its replacement is started.
+	#[test]
+	fn subsystem_lifecycle_hooks_are_called_in_order() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ReportsLifecycle("old", tx.clone())),
+				Box::new(IdleSubsystem),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let replacement = AnySubsystem::Validation(Box::new(ReportsLifecycle("new", tx)));
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				replaced = handler.replace_subsystem(replacement).fuse() => {
+					assert_eq!(replaced, Ok(SubsystemId::Validation));
+				}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+
+			let mut calls = Vec::new();
+			while let Ok(call) = rx.try_recv() {
+				calls.push(call);
+			}
+			assert_eq!(calls, vec![
+				("old", "on_start"),
+				("old", "start"),
+				("old", "concluded"),
+				("old", "on_stop"),
+				("new", "on_start"),
+				("new", "start"),
+				("new", "concluded"),
+				("new", "on_stop"),
+			]);
+		})
+	}
+
+	// Checks that the time spent waiting on a slow subsystem is put down to it.
+	#[cfg(feature = "timing")]
+	#[test]
+	fn overseer_times_slow_subsystem() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (s1_tx, _) = mpsc::channel(64);
+			let slow = FnSubsystem::new(|mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| async move {
+				while let FromOverseer::Communication { .. } = ctx.recv().await? {
+					Delay::new(Duration::from_millis(20)).await;
+				}
+
+				ctx.conclude()
+			});
+			let config = OverseerConfig {
+				candidate_backing_channels: ChannelConfig {
+					inbound: 1,
+					..Default::default()
+				},
+				overflow_capacity: 0,
+				mailbox_policy: MailboxPolicy::Wait,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(TestSubsystem1(s1_tx)),
+				Box::new(slow),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			for _ in 0..5 {
+				handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			}
+
+			let timings = select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				timings = handler.timings().fuse() => timings.unwrap(),
+			};
+			assert!(timings.dispatch[&SubsystemId::CandidateBacking] >= Duration::from_millis(40));
+			assert!(!timings.dispatch.contains_key(&SubsystemId::Validation));
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a subsystem that has dropped its context is found out once it is sent
+	// a message, and is stopped instead of being sent to again. Its future is left to
+	// finish, and finishing fine is not taken for a failure.
+	#[test]
+	fn overseer_stops_subsystem_that_dropped_its_context() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let (dropped_tx, dropped_rx) = oneshot::channel();
+			let (finish_tx, finish_rx) = oneshot::channel::<()>();
+			let mut channels = Some((dropped_tx, finish_rx));
+			let dropping = FnSubsystem::new(move |ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let channels = channels.take();
+
+				async move {
+					drop(ctx);
+					if let Some((dropped_tx, finish_rx)) = channels {
+						let _ = dropped_tx.send(());
+						let _ = finish_rx.await;
+					}
+
+					Ok(())
+				}
+			});
+			let config = OverseerConfig {
+				exit_policy: ExitPolicy::Ignore,
+				restart_policy: None,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(dropping),
+				Box::new(IdleSubsystem),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			dropped_rx.await.unwrap();
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				event = events.next() => assert_eq!(event, Some(OverseerEvent::SubsystemStopped(SubsystemId::Validation))),
+			}
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				ids = handler.list_subsystems().fuse() => assert_eq!(ids.unwrap(), vec![SubsystemId::CandidateBacking]),
+			}
+
+			drop(finish_tx);
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => assert_eq!(res, Ok(())),
+			}
+
+			let mut idle = Delay::new(Duration::from_millis(50)).fuse();
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				event = events.next() => panic!("Unexpected event {:?}", event),
+				_ = idle => (),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks how the logs refer to a named overseer and its subsystems.
+	#[test]
+	fn logs_show_names_along_with_ids() {
+		let spawner = executor::ThreadPool::new().unwrap();
+		let (s1_tx, _) = mpsc::channel(64);
+		let (s2_tx, _) = mpsc::channel(64);
+		let (overseer, _) = Overseer::new(
+			Box::new(TestSubsystem1(s1_tx)),
+			Box::new(TestSubsystem2(s2_tx)),
+			spawner,
+		).unwrap();
 
-		let handler = OverseerHandler {
-			events_tx: events_tx.clone(),
-		};
+		assert_eq!(format!("{}{:?}", overseer.prefix(), overseer.named(SubsystemId::Validation)), "Validation");
 
-		let mut running_subsystems_rx = StreamUnordered::new();
-		let mut running_subsystems = FuturesUnordered::new();
+		let overseer = overseer
+			.with_name("relay")
+			.with_subsystem_name(SubsystemId::Validation, "candidate-validation");
+		assert_eq!(
+			format!("{}{:?}", overseer.prefix(), overseer.named(SubsystemId::Validation)),
+			"relay: candidate-validation (Validation)",
+		);
+		assert_eq!(format!("{:?}", overseer.named(SubsystemId::CandidateBacking)), "CandidateBacking");
+	}
 
-		let validation_subsystem = spawn(
-			&mut s,
-			&mut running_subsystems,
-			&mut running_subsystems_rx,
-			validation,
-		)?;
+	// Checks that a subsystem answers a request of another one through its `Responder`.
+	#[test]
+	fn request_is_answered_through_responder() {
+		let spawner = executor::ThreadPool::new().unwrap();
 
-		let candidate_backing_subsystem = spawn(
-			&mut s,
-			&mut running_subsystems,
-			&mut running_subsystems_rx,
-			candidate_backing,
-		)?;
+		executor::block_on(async move {
+			let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				loop {
+					match ctx.recv_request().await? {
+						(FromOverseer::Communication { .. }, Some(responder)) => responder.send(true)?,
+						(FromOverseer::Signal(OverseerSignal::Conclude), _) => return ctx.conclude(),
+						_ => (),
+					}
+				}
+			});
+			let (answer_tx, mut answer_rx) = mpsc::unbounded();
+			let candidate_backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let answer_tx = answer_tx.clone();
 
-		let this = Self {
-			validation_subsystem,
-			candidate_backing_subsystem,
-			s,
-			running_subsystems,
-			running_subsystems_rx,
-			events_rx,
-		};
+				async move {
+					let valid = ctx.send_and_recv(|tx| {
+						AllMessages::Validation(ValidationSubsystemMessage::Validate(tx.into()))
+					}).await?;
+					answer_tx.unbounded_send(valid).unwrap();
 
-		Ok((this, handler))
+					while let FromOverseer::Communication { .. } = ctx.recv().await? {}
+					ctx.conclude()
+				}
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				valid = answer_rx.next() => assert_eq!(valid, Some(true)),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
 	}
 
-	// Stop the overseer.
-	async fn stop(mut self) {
-		if let Some(ref mut s) = self.validation_subsystem.instance {
-			let _ = s.tx.send(FromOverseer::Signal(OverseerSignal::Conclude)).await;
-		}
+	// Checks that an overseer can be created from the topology of another one,
+	// starting only its subsystems and keeping their restarts.
+	#[test]
+	fn overseer_can_be_created_from_topology() {
+		let spawner = executor::ThreadPool::new().unwrap();
 
-		if let Some(ref mut s) = self.candidate_backing_subsystem.instance {
-			let _ = s.tx.send(FromOverseer::Signal(OverseerSignal::Conclude)).await;
-		}
+		executor::block_on(async move {
+			let dependencies = vec![(SubsystemId::CandidateBacking, SubsystemId::Validation)];
+			let config = OverseerConfig {
+				dependencies: dependencies.clone(),
+				..Default::default()
+			};
+			let (overseer, _) = Overseer::with_config(
+				Box::new(ReadyOnFirstMessage),
+				Box::new(ReadyOnFirstMessage),
+				spawner.clone(),
+				config,
+			).unwrap();
 
-		let mut stop_delay = Delay::new(Duration::from_secs(STOP_DELAY)).fuse();
+			assert_eq!(overseer.topology(), OverseerTopology {
+				subsystems: vec![SubsystemId::Validation, SubsystemId::CandidateBacking],
+				dependencies: dependencies.clone(),
+				restarts: SubsystemId::ALL.iter().map(|id| (*id, 0)).collect(),
+			});
+			drop(overseer);
 
-		loop {
-			select! {
-				_ = self.running_subsystems.next() => {
-					if self.running_subsystems.is_empty() {
-						break;
-					}
+			let topology = OverseerTopology {
+				subsystems: vec![SubsystemId::CandidateBacking],
+				dependencies,
+				restarts: vec![(SubsystemId::CandidateBacking, 2)].into_iter().collect(),
+			};
+			let (overseer, mut handler) = Overseer::from_topology(
+				topology.clone(),
+				|id| match id {
+					SubsystemId::Validation => AnySubsystem::Validation(Box::new(ReadyOnFirstMessage)),
+					SubsystemId::CandidateBacking => AnySubsystem::CandidateBacking(Box::new(ReadyOnFirstMessage)),
 				},
-				_ = stop_delay => break,
-				complete => break,
-			}
-		}
-	}
+				spawner,
+				OverseerConfig::default(),
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
 
-	/// Run the `Overseer`.
-	pub async fn run(mut self) -> SubsystemResult<()> {
-		loop {
-			while let Poll::Ready(Some(msg)) = poll!(&mut self.events_rx.next()) {
-				match msg {
-					Event::MsgToSubsystem(msg) => {
-						self.route_message(msg).await;
-					}
-					Event::Stop => {
-						self.stop().await;
-						return Ok(());
-					}
-					_ => ()
-				}
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.topology().fuse() => assert_eq!(res.unwrap(), topology),
 			}
 
-			while let Poll::Ready(Some((StreamYield::Item(msg), _))) = poll!(
-				&mut self.running_subsystems_rx.next()
-			) {
-				match msg {
-					ToOverseer::SubsystemMessage(msg) => self.route_message(msg).await,
-					ToOverseer::SpawnJob { s, res } => {
-						let s = self.spawn_job(s);
-
-						let _ = res.send(s);
-					}
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				info = handler.inspect().fuse() => {
+					let info = info.unwrap();
+					assert!(!info[0].running);
+					assert!(info[1].running);
+					assert_eq!(info[1].restarts, 2);
 				}
 			}
 
-			// Some subsystem exited? It's time to panic.
-			if let Poll::Ready(Some(finished)) = poll!(self.running_subsystems.next()) {
-				log::error!("Subsystem finished unexpectedly {:?}", finished);
-				self.stop().await;
-				return Err(SubsystemError);
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				res = handler.await_subsystem(SubsystemId::Validation).fuse() => {
+					assert_eq!(res, Err(SubsystemError::UnknownSubsystem(SubsystemId::Validation)));
+				}
 			}
 
-			// Looks like nothing is left to be polled, let's take a break.
-			pending!();
-		}
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
 	}
 
-	async fn route_message(&mut self, msg: AllMessages) {
-		match msg {
-			AllMessages::Validation(msg) => {
-				if let Some(ref mut s) = self.validation_subsystem.instance {
-					let _= s.tx.send(FromOverseer::Communication { msg }).await;
+
+	// Checks that a message whose deadline has passed by the time it is dispatched
+	// is dropped rather than delivered late.
+	#[test]
+	fn overseer_drops_expired_message() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let validation = FnSubsystem::new(|mut ctx: SubsystemContext<ValidationSubsystemMessage>| async move {
+				ctx.recv().await?;
+
+				let now = ctx.clock().now();
+				ctx.send_msg_with_deadline(
+					AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second),
+					now,
+				).await?;
+				ctx.send_msg_with_deadline(
+					AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher),
+					now + Duration::from_secs(60),
+				).await?;
+
+				while ctx.recv().await.is_ok() {}
+				Ok(())
+			});
+			let (tx, mut rx) = mpsc::unbounded();
+			let backing = FnSubsystem::new(move |mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>| {
+				let tx = tx.clone();
+
+				async move {
+					while let FromOverseer::Communication { msg } = ctx.recv().await? {
+						tx.unbounded_send(msg.kind()).unwrap();
+					}
+					Ok(())
 				}
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(backing),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			let mut events = handler.events().await.unwrap();
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				kind = rx.next() => assert_eq!(kind, Some("RegisterBackingWatcher")),
 			}
-			AllMessages::CandidateBacking(msg) => {
-				if let Some(ref mut s) = self.candidate_backing_subsystem.instance {
-					let _ = s.tx.send(FromOverseer::Communication { msg }).await;
+
+			// The expired message is dropped as it is dispatched, before the one sent after it.
+			loop {
+				match events.next().await {
+					Some(OverseerEvent::MessageExpired { from, to }) => {
+						assert_eq!(from, Some(SubsystemId::Validation));
+						assert_eq!(to, SubsystemId::CandidateBacking);
+						break;
+					}
+					Some(_) => (),
+					None => panic!("No message has expired"),
 				}
 			}
-		}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+			assert_eq!(rx.next().await, None);
+		})
 	}
 
 
-	fn spawn_job(&mut self, j: BoxFuture<'static, ()>) -> SubsystemResult<()> {
-		self.s.spawn(j).map_err(|_| SubsystemError)
-	}
-}
+	// Checks that the overseer tells why it has stopped and which subsystems were still running.
+	#[test]
+	fn overseer_reports_why_it_has_stopped() {
+		let spawner = executor::ThreadPool::new().unwrap();
 
-fn spawn<S: Spawn, M: Debug>(
-	spawner: &mut S,
-	futures: &mut FuturesUnordered<RemoteHandle<()>>,
-	streams: &mut StreamUnordered<mpsc::Receiver<ToOverseer>>,
-	mut s: Box<dyn Subsystem<M> + Send>,
-) -> SubsystemResult<OverseenSubsystem<M>> {
-	let (to_tx, to_rx) = mpsc::channel(CHANNEL_CAPACITY);
-	let (from_tx, from_rx) = mpsc::channel(CHANNEL_CAPACITY);
-	let ctx = SubsystemContext::new(to_rx, from_tx);
-	let f = s.start(ctx);
+		executor::block_on(async move {
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(ReadyOnFirstMessage),
+				Box::new(ReadyOnFirstMessage),
+				spawner.clone(),
+			).unwrap();
+			let overseer_fut = overseer.run().fuse();
+			pin_mut!(overseer_fut);
 
-	let handle = spawner.spawn_with_handle(f.0)?;
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await, ShutdownReport {
+				reason: ShutdownReason::ExternalStop,
+				running: vec![SubsystemId::Validation, SubsystemId::CandidateBacking],
+			});
 
-	streams.push(from_rx);
-	futures.push(handle);
+			let failing = FnSubsystem::new(|_: SubsystemContext<ValidationSubsystemMessage>| async {
+				Err(SubsystemError::SubsystemPanicked)
+			});
+			let (overseer, _handler) = Overseer::new(
+				Box::new(failing),
+				Box::new(ReadyOnFirstMessage),
+				spawner,
+			).unwrap();
 
-	let instance = Some(SubsystemInstance {
-		tx: to_tx,
-	});
+			assert_eq!(overseer.run().await, ShutdownReport {
+				reason: ShutdownReason::SubsystemFatal(SubsystemId::Validation, SubsystemError::SubsystemPanicked),
+				running: vec![SubsystemId::CandidateBacking],
+			});
+		})
+	}
 
-	Ok(OverseenSubsystem {
-		subsystem: s,
-		instance,
-	})
-}
 
-#[cfg(test)]
-mod tests {
-	use futures::{executor, pin_mut, select, channel::mpsc, FutureExt};
-	use super::*;
+	// Checks that the last dispatched messages are kept up to the capacity, the oldest first.
+	#[test]
+	fn overseer_keeps_recent_dispatches() {
+		let spawner = executor::ThreadPool::new().unwrap();
 
-	struct TestSubsystem1(mpsc::Sender<usize>);
+		executor::block_on(async move {
+			let (tx, _rx) = mpsc::unbounded();
+			let config = OverseerConfig {
+				dispatch_log_capacity: 2,
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(ReportsIdOnStart(tx.clone())),
+				Box::new(ReportsIdOnStart(tx)),
+				spawner,
+				config,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
 
-	impl Subsystem<ValidationSubsystemMessage> for TestSubsystem1 {
-		fn start(&mut self, mut ctx: SubsystemContext<ValidationSubsystemMessage>) -> SpawnedSubsystem {
-			let mut sender = self.0.clone();
-			SpawnedSubsystem(Box::pin(async move {
-				let mut i = 0;
-				loop {
-					match ctx.recv().await {
-						Ok(FromOverseer::Communication { .. }) => {
-							let _ = sender.send(i).await;
-							i += 1;
-							continue;
-						}
-						Ok(FromOverseer::Signal(OverseerSignal::StopWork)) => return,
-						Err(_) => return,
-						_ => (),
-					}
+			handler.send_msg(AllMessages::Validation(ValidationSubsystemMessage::ValidityAttestation)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::Second)).await.unwrap();
+			handler.send_msg(AllMessages::CandidateBacking(CandidateBackingSubsystemMessage::RegisterBackingWatcher)).await.unwrap();
+
+			// The messages are dispatched as they come, before the entries are asked for.
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				entries = handler.recent_dispatches().fuse() => {
+					let entries: Vec<_> = entries.unwrap().into_iter()
+						.map(|entry| (entry.from, entry.to, entry.kind))
+						.collect();
+					assert_eq!(entries, vec![
+						(None, SubsystemId::CandidateBacking, "Second"),
+						(None, SubsystemId::CandidateBacking, "RegisterBackingWatcher"),
+					]);
 				}
-			}))
-		}
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
 	}
 
-	struct TestSubsystem2(mpsc::Sender<usize>);
 
-	impl Subsystem<CandidateBackingSubsystemMessage> for TestSubsystem2 {
-		fn start(&mut self, mut ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
-			SpawnedSubsystem(Box::pin(async move {
-				let mut c: usize = 0;
-				loop {
-					if c < 10 {
-						ctx.send_msg(
-							AllMessages::Validation(
-								ValidationSubsystemMessage::ValidityAttestation
-							)
-						).await.unwrap();
-						c += 1;
-						continue;
-					}
-					match ctx.try_recv().await {
-						Ok(Some(FromOverseer::Signal(OverseerSignal::StopWork))) => {
-							break;
-						}
-						Ok(Some(_)) => {
-							continue;
-						}
-						Err(_) => return,
-						_ => (),
-					}
-					pending!();
+	// Checks that an overseer left without subsystems stops if it is told to,
+	// and otherwise waits for new ones to be spawned.
+	#[test]
+	fn overseer_without_subsystems_stops_or_waits() {
+		let spawner = executor::ThreadPool::new().unwrap();
+
+		executor::block_on(async move {
+			let concluding = || (
+				FnSubsystem::new(|ctx: SubsystemContext<ValidationSubsystemMessage>| async move { ctx.conclude() }),
+				FnSubsystem::new(|ctx: SubsystemContext<CandidateBackingSubsystemMessage>| async move { ctx.conclude() }),
+			);
+
+			let (validation, candidate_backing) = concluding();
+			let config = OverseerConfig {
+				stop_when_empty: true,
+				..Default::default()
+			};
+			let (overseer, _handler) = Overseer::with_config(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner.clone(),
+				config,
+			).unwrap();
+
+			assert_eq!(overseer.run().await, ShutdownReport {
+				reason: ShutdownReason::Clean,
+				running: Vec::new(),
+			});
+
+			let (validation, candidate_backing) = concluding();
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(candidate_backing),
+				spawner,
+			).unwrap();
+			let overseer_fut = overseer.fuse();
+			pin_mut!(overseer_fut);
+
+			for id in &SubsystemId::ALL {
+				select! {
+					_ = overseer_fut => panic!("Overseer exited early"),
+					res = handler.await_subsystem(*id).fuse() => assert_eq!(res, Ok(())),
 				}
-			}))
-		}
-	}
+			}
 
-	struct TestSubsystem4;
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				id = handler.spawn_subsystem(AnySubsystem::Validation(Box::new(ReadyOnFirstMessage))).fuse() => {
+					assert_eq!(id, Ok(SubsystemId::Validation));
+				}
+			}
 
-	impl Subsystem<CandidateBackingSubsystemMessage> for TestSubsystem4 {
-		fn start(&mut self, mut _ctx: SubsystemContext<CandidateBackingSubsystemMessage>) -> SpawnedSubsystem {
-			SpawnedSubsystem(Box::pin(async move {
-				// Do nothing and exit.
-			}))
-		}
+			select! {
+				_ = overseer_fut => panic!("Overseer exited early"),
+				ids = handler.list_subsystems().fuse() => assert_eq!(ids.unwrap(), vec![SubsystemId::Validation]),
+			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
 	}
 
-	// Checks that a minimal configuration of two jobs can run and exchange messages.
+	// Drain the overseer with a subsystem that never concludes.
+	//
+	// The overseer should stop it once the drain timeout has passed,
+	// rather than wait for it forever.
 	#[test]
-	fn overseer_works() {
+	fn overseer_stops_subsystems_that_do_not_drain() {
 		let spawner = executor::ThreadPool::new().unwrap();
 
 		executor::block_on(async move {
 			let (s1_tx, mut s1_rx) = mpsc::channel(64);
-			let (s2_tx, mut s2_rx) = mpsc::channel(64);
-
-			let (overseer, mut handler) = Overseer::new(
-				Box::new(TestSubsystem1(s1_tx)),
-				Box::new(TestSubsystem2(s2_tx)),
+			let config = OverseerConfig {
+				drain_timeout: Duration::from_millis(100),
+				stop_grace_period: Duration::from_millis(100),
+				..Default::default()
+			};
+			let (overseer, mut handler) = Overseer::with_config(
+				Box::new(SlowCounter(s1_tx)),
+				Box::new(NeverConcludes),
 				spawner,
+				config,
 			).unwrap();
 			let overseer_fut = overseer.run().fuse();
-
 			pin_mut!(overseer_fut);
 
-			let mut s1_results = Vec::new();
-			let mut s2_results = Vec::new();
+			handler.drain().await.unwrap();
 
-			loop {
-				select! {
-					a = overseer_fut => break,
-					s1_next = s1_rx.next() => {
-						match s1_next {
-							Some(msg) => {
-								s1_results.push(msg);
-								if s1_results.len() == 10 {
-									handler.stop().await.unwrap();
-								}
-							}
-							None => break,
-						}
-					},
-					s2_next = s2_rx.next() => {
-						match s2_next {
-							Some(msg) => s2_results.push(s2_next),
-							None => break,
-						}
-					},
-					complete => break,
-				}
-			}
+			let report = overseer_fut.await;
+			assert_eq!(report.reason, ShutdownReason::ExternalStop);
+			assert_eq!(report.running, vec![SubsystemId::CandidateBacking]);
+			assert_eq!(s1_rx.next().await, Some(0));
+		})
+	}
 
-			assert_eq!(s1_results, (0..10).collect::<Vec<_>>());
-		});
+	// Checks that the default pool starts its threads only once there is blocking work.
+	#[test]
+	fn blocking_pool_is_created_lazily() {
+		let pool = LazyThreadPool::default();
+		assert!(pool.pool.lock().unwrap().is_none());
+
+		let (tx, rx) = oneshot::channel();
+		pool.spawn_blocking(Box::new(move || {
+			let _ = tx.send(());
+		}));
+
+		assert!(pool.pool.lock().unwrap().is_some());
+		assert_eq!(executor::block_on(rx), Ok(()));
 	}
 
-	// Spawn a subsystem that immediately exits.
-	//
-	// Should immediately conclude the overseer itself with an error.
+	// Checks that without a configured pool blocking work runs on a pool of its own.
 	#[test]
-	fn overseer_panics_on_sybsystem_exit() {
+	fn blocking_work_runs_on_a_pool_of_its_own() {
 		let spawner = executor::ThreadPool::new().unwrap();
 
 		executor::block_on(async move {
-			let (s1_tx, _) = mpsc::channel(64);
-			let (overseer, _handle) = Overseer::new(
-				Box::new(TestSubsystem1(s1_tx)),
-				Box::new(TestSubsystem4),
+			let (tx, mut rx) = mpsc::unbounded();
+			let validation = FnSubsystem::new(move |ctx: SubsystemContext<ValidationSubsystemMessage>| {
+				let tx = tx.clone();
+				async move {
+					let name = ctx.spawn_blocking(|| std::thread::current().name().map(String::from)).await?;
+					tx.unbounded_send(name).unwrap();
+					ctx.conclude()
+				}
+			});
+			let (overseer, mut handler) = Overseer::new(
+				Box::new(validation),
+				Box::new(ConcludesAtOnce),
 				spawner,
 			).unwrap();
 			let overseer_fut = overseer.run().fuse();
 			pin_mut!(overseer_fut);
 
 			select! {
-				res = overseer_fut => assert!(res.is_err()),
-				complete => (),
+				_ = overseer_fut => panic!("Overseer exited early"),
+				name = rx.next() => match name {
+					Some(Some(name)) => assert!(name.starts_with("overseer-blocking-"), "Unexpected thread {}", name),
+					name => panic!("Unexpected thread {:?}", name),
+				},
 			}
+
+			handler.stop().await.unwrap();
+			assert_eq!(overseer_fut.await.reason, ShutdownReason::ExternalStop);
+		})
+	}
+
+	// Checks that a message too long to be read on the other side is not written.
+	#[cfg(feature = "proxy")]
+	#[test]
+	fn proxy_does_not_write_overlong_frames() {
+		use proxy::{write_frame, MAX_FRAME_LEN};
+
+		executor::block_on(async move {
+			let (tx, mut rx) = mpsc::unbounded();
+			let mut w = ChannelWriter(tx);
+
+			let res = write_frame(&mut w, &"x".repeat(MAX_FRAME_LEN)).await;
+			assert_eq!(res, Err(SubsystemError::ChannelClosed));
+			assert!(rx.try_recv().unwrap_err().is_empty());
+
+			write_frame(&mut w, &"x").await.unwrap();
+			assert!(rx.try_recv().is_ok());
 		})
 	}
 }
@@ -0,0 +1,231 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Running a [`Subsystem`] in another process.
+//!
+//! A [`ProxySubsystem`] stands in for the [`Subsystem`] inside of the [`Overseer`]
+//! and forwards what it receives to the other process over a pair of pipes, or any
+//! other byte streams. The other process answers with the [`AllMessages`] to be sent
+//! on, which the `ProxySubsystem` sends as if they came from itself.
+//!
+//! Each message is encoded as JSON and framed by its length as a big endian `u32`.
+//! The other process reads [`FromOverseer`] messages and writes [`AllMessages`]
+//! with [`read_frame`] and [`write_frame`].
+//!
+//! [`Subsystem`]: ../trait.Subsystem.html
+//! [`Overseer`]: ../struct.Overseer.html
+//! [`AllMessages`]: ../enum.AllMessages.html
+//! [`FromOverseer`]: ../enum.FromOverseer.html
+//! [`ProxySubsystem`]: struct.ProxySubsystem.html
+//! [`read_frame`]: fn.read_frame.html
+//! [`write_frame`]: fn.write_frame.html
+
+use std::fmt::Debug;
+use std::io;
+
+use futures::channel::mpsc;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::{pin_mut, select, Future, FutureExt, SinkExt};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+	AllMessages, FromOverseer, MessageSpan, OverseerSignal, Priority, SpawnedSubsystem, Subsystem,
+	SubsystemContext, SubsystemError, SubsystemResult, ToOverseer,
+};
+
+/// The largest frame that is read, anything longer is taken for a broken stream.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Write a message as one frame.
+///
+/// Any failure to write means that the other side is gone and is reported as
+/// `SubsystemError::ChannelClosed`. So is a message that can not be encoded or is
+/// longer than [`MAX_FRAME_LEN`], which the other side would not read, and nothing
+/// is written then. The message is encoded right away, so the returned future does
+/// not borrow it.
+///
+/// [`MAX_FRAME_LEN`]: constant.MAX_FRAME_LEN.html
+pub fn write_frame<'a, W, T>(w: &'a mut W, msg: &T) -> impl Future<Output = SubsystemResult<()>> + 'a
+where
+	W: AsyncWrite + Unpin,
+	T: Serialize,
+{
+	let payload = serde_json::to_vec(msg).map_err(|e| {
+		log::error!("Failed to encode a message for another process {:?}", e);
+		SubsystemError::ChannelClosed
+	}).and_then(|payload| {
+		if payload.len() > MAX_FRAME_LEN {
+			log::error!("Message of {} bytes for another process is too long", payload.len());
+			return Err(SubsystemError::ChannelClosed);
+		}
+
+		Ok(payload)
+	});
+
+	async move {
+		let payload = payload?;
+
+		w.write_all(&(payload.len() as u32).to_be_bytes()).await.map_err(closed)?;
+		w.write_all(&payload).await.map_err(closed)?;
+		w.flush().await.map_err(closed)
+	}
+}
+
+/// Read a message from one frame.
+///
+/// The end of the stream, a frame longer than [`MAX_FRAME_LEN`] and a message that
+/// can not be decoded all mean that the other side is gone or broken, and are reported
+/// as `SubsystemError::ChannelClosed`.
+///
+/// [`MAX_FRAME_LEN`]: constant.MAX_FRAME_LEN.html
+pub async fn read_frame<R, T>(r: &mut R) -> SubsystemResult<T>
+where
+	R: AsyncRead + Unpin,
+	T: DeserializeOwned,
+{
+	let mut len = [0; 4];
+	r.read_exact(&mut len).await.map_err(closed)?;
+
+	let len = u32::from_be_bytes(len) as usize;
+	if len > MAX_FRAME_LEN {
+		log::error!("Frame of {} bytes from another process is too long", len);
+		return Err(SubsystemError::ChannelClosed);
+	}
+
+	let mut payload = vec![0; len];
+	r.read_exact(&mut payload).await.map_err(closed)?;
+
+	serde_json::from_slice(&payload).map_err(|e| {
+		log::error!("Failed to decode a message from another process {:?}", e);
+		SubsystemError::ChannelClosed
+	})
+}
+
+fn closed(e: io::Error) -> SubsystemError {
+	log::debug!("The stream to another process has failed {:?}", e);
+	SubsystemError::ChannelClosed
+}
+
+/// A [`Subsystem`] that is run by another process.
+///
+/// The other process is connected to whenever the `ProxySubsystem` is started,
+/// so a restarted `ProxySubsystem` may start a new process. Once the other
+/// process has gone away the `ProxySubsystem` fails with
+/// `SubsystemError::ChannelClosed` and is handled by the exit policy.
+///
+/// The `ProxySubsystem` concludes once it has passed the `Conclude` signal on to
+/// the other process. It does so as well when the `Overseer` drops its channels.
+///
+/// [`Subsystem`]: ../trait.Subsystem.html
+pub struct ProxySubsystem<C> {
+	connect: C,
+}
+
+impl<C> ProxySubsystem<C> {
+	/// Create a `ProxySubsystem` that connects to the other process with `connect`.
+	///
+	/// `connect` returns the stream to read from the other process and the one to write to it,
+	/// such as the stdout and the stdin of a child process.
+	pub fn new(connect: C) -> Self {
+		Self { connect }
+	}
+}
+
+impl<M, C, R, W> Subsystem<M> for ProxySubsystem<C>
+where
+	M: Debug + Serialize + Send + 'static,
+	C: FnMut() -> io::Result<(R, W)>,
+	R: AsyncRead + Unpin + Send + 'static,
+	W: AsyncWrite + Unpin + Send + 'static,
+{
+	fn start(&mut self, ctx: SubsystemContext<M>) -> SpawnedSubsystem {
+		let transport = (self.connect)();
+
+		SpawnedSubsystem(Box::pin(async move {
+			let (r, w) = transport.map_err(|e| {
+				log::error!("Failed to connect to another process {:?}", e);
+				SubsystemError::ChannelClosed
+			})?;
+
+			bridge(ctx, r, w).await
+		}))
+	}
+}
+
+// Forward the messages to the other process and its messages to the overseer,
+// until either of them is gone.
+//
+// The two directions are forwarded concurrently, so that neither waits for the
+// other: the other process may only read once its replies have been read, and
+// the overseer may only take the replies once its messages have been received.
+async fn bridge<M, R, W>(mut ctx: SubsystemContext<M>, r: R, w: W) -> SubsystemResult<()>
+where
+	M: Debug + Serialize,
+	R: AsyncRead + Unpin,
+	W: AsyncWrite + Unpin,
+{
+	{
+		let replies = forward_replies(r, ctx.tx.clone()).fuse();
+		let messages = relay_messages(&mut ctx, w).fuse();
+		pin_mut!(replies, messages);
+
+		select! {
+			res = messages => res?,
+			res = replies => res?,
+		}
+	}
+
+	ctx.conclude()
+}
+
+// Write what the overseer sends to the other process until it has been told to conclude.
+//
+// An overseer that has dropped the channels is gone for good, so the other process
+// is told to conclude then as well.
+async fn relay_messages<M, W>(ctx: &mut SubsystemContext<M>, mut w: W) -> SubsystemResult<()>
+where
+	M: Debug + Serialize,
+	W: AsyncWrite + Unpin,
+{
+	loop {
+		let msg = ctx.recv().await.unwrap_or(FromOverseer::Signal(OverseerSignal::Conclude));
+		let conclude = matches!(msg, FromOverseer::Signal(OverseerSignal::Conclude));
+
+		write_frame(&mut w, &msg).await?;
+
+		if conclude {
+			return Ok(());
+		}
+	}
+}
+
+// Send the messages the other process writes on to the overseer, until either of them is gone.
+async fn forward_replies<R>(mut r: R, mut tx: mpsc::Sender<ToOverseer>) -> SubsystemResult<()>
+where
+	R: AsyncRead + Unpin,
+{
+	loop {
+		let msg = read_frame::<_, AllMessages>(&mut r).await?;
+
+		tx.send(ToOverseer::SubsystemMessage {
+			msg,
+			causation_id: None,
+			priority: Priority::Normal,
+			deadline: None,
+			span: MessageSpan::none(),
+		}).await?;
+	}
+}
@@ -0,0 +1,215 @@
+// Copyright 2020 Parity Technologies (UK) Ltd.
+// This file is part of Polkadot.
+
+// Polkadot is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// Polkadot is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with Polkadot.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Utilities for testing a single [`Subsystem`] without an [`Overseer`].
+//!
+//! [`Subsystem`]: ../trait.Subsystem.html
+//! [`Overseer`]: ../struct.Overseer.html
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::channel::{mpsc, oneshot};
+use futures::executor::ThreadPool;
+use futures::future::{self, BoxFuture, Either};
+use futures::task::SpawnExt;
+use futures::{stream, FutureExt, StreamExt};
+
+use crate::{
+	AllMessages, Clock, Envelope, FromOverseer, Metrics, MessageId, MessageSpan, OverseerSignal,
+	SubsystemContext, SubsystemId, SystemClock, ToOverseer, CHANNEL_CAPACITY,
+};
+
+/// The other ends of the channels of a [`SubsystemContext`] that is not connected to an `Overseer`.
+///
+/// The test takes the place of the `Overseer`: it feeds the `Subsystem` with messages
+/// and signals and checks what the `Subsystem` sends. The jobs the `Subsystem` spawns
+/// and its blocking work are run on a `ThreadPool`.
+///
+/// [`SubsystemContext`]: ../struct.SubsystemContext.html
+pub struct TestContext<M: Debug> {
+	to_tx: mpsc::UnboundedSender<Envelope<M>>,
+	signals_tx: mpsc::UnboundedSender<OverseerSignal>,
+	from_rx: stream::Select<mpsc::Receiver<ToOverseer>, mpsc::UnboundedReceiver<ToOverseer>>,
+	cancel: Option<oneshot::Sender<()>>,
+	pool: ThreadPool,
+	next_message_id: u64,
+}
+
+impl<M: Debug> TestContext<M> {
+	/// Create a context for the `Subsystem` with the given id and the handle to test it through.
+	pub fn new(id: SubsystemId) -> (SubsystemContext<M>, Self) {
+		Self::with_clock(id, Arc::new(SystemClock))
+	}
+
+	/// Like [`new`], but the `Subsystem` takes the time from `clock`, such as a [`TestClock`].
+	///
+	/// [`new`]: #method.new
+	/// [`TestClock`]: struct.TestClock.html
+	pub fn with_clock(id: SubsystemId, clock: Arc<dyn Clock>) -> (SubsystemContext<M>, Self) {
+		let pool = ThreadPool::new().expect("a thread pool can be created in tests");
+		let (to_tx, to_rx) = mpsc::unbounded();
+		let (signals_tx, signals_rx) = mpsc::unbounded();
+		let (from_tx, from_rx) = mpsc::channel(CHANNEL_CAPACITY);
+		let (posts_tx, posts_rx) = mpsc::unbounded();
+		let (cancel, cancelled) = oneshot::channel();
+
+		let ctx = SubsystemContext::new(
+			id,
+			Either::Right(to_rx),
+			signals_rx,
+			from_tx,
+			posts_tx,
+			Arc::new(pool.clone()),
+			Arc::new(Metrics::default()),
+			Arc::new(pool.clone()),
+			clock,
+			cancelled,
+		);
+
+		let handle = Self {
+			to_tx,
+			signals_tx,
+			from_rx: stream::select(from_rx, posts_rx),
+			cancel: Some(cancel),
+			pool,
+			next_message_id: 0,
+		};
+
+		(ctx, handle)
+	}
+
+	/// Send a message to the `Subsystem`, as if it came from another one.
+	pub fn send_to_subsystem(&mut self, msg: M) {
+		let id = MessageId(self.next_message_id);
+		self.next_message_id += 1;
+
+		self.to_tx.unbounded_send(Envelope {
+			id: Some(id),
+			msg: FromOverseer::Communication { msg },
+			span: MessageSpan::none(),
+		}).expect("the subsystem has dropped its context");
+	}
+
+	/// Send a signal to the `Subsystem`.
+	pub fn send_signal(&mut self, signal: OverseerSignal) {
+		self.signals_tx.unbounded_send(signal).expect("the subsystem has dropped its context");
+	}
+
+	/// Ask for the work of the `Subsystem` to be cancelled, as the `Overseer` does when it stops.
+	pub fn cancel(&mut self) {
+		if let Some(cancel) = self.cancel.take() {
+			let _ = cancel.send(());
+		}
+	}
+
+	/// Wait for the next message the `Subsystem` sends to another one.
+	///
+	/// The jobs the `Subsystem` spawns meanwhile are run, and its readiness
+	/// and heartbeats are skipped over. It has no peers to be told of.
+	///
+	/// # Panics
+	///
+	/// If the `Subsystem` drops its context instead.
+	pub async fn expect_msg(&mut self) -> AllMessages {
+		loop {
+			match self.from_rx.next().await {
+				Some(ToOverseer::SubsystemMessage { msg, .. }) => return msg,
+				Some(ToOverseer::SpawnJob { s, res }) => {
+					let _ = res.send(self.pool.spawn(s).map_err(Into::into));
+				}
+				Some(ToOverseer::Peers(tx)) => {
+					let _ = tx.send(Vec::new());
+				}
+				Some(ToOverseer::Ready) | Some(ToOverseer::Heartbeat) | Some(ToOverseer::Concluded) => (),
+				None => panic!("the subsystem has dropped its context"),
+			}
+		}
+	}
+}
+
+/// A [`Clock`] that only moves on when it is told to.
+///
+/// Clones share the time, so a test keeps one and hands out another to the
+/// `Overseer` or a [`TestContext`]. Whatever sleeps on it wakes up once the
+/// clock has been advanced past its deadline.
+///
+/// [`Clock`]: ../trait.Clock.html
+/// [`TestContext`]: struct.TestContext.html
+#[derive(Debug, Clone)]
+pub struct TestClock(Arc<Mutex<TestClockState>>);
+
+#[derive(Debug)]
+struct TestClockState {
+	now: Instant,
+	sleeping: Vec<(Instant, oneshot::Sender<()>)>,
+}
+
+impl TestClock {
+	/// Create a clock that starts at the current time.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(TestClockState {
+			now: Instant::now(),
+			sleeping: Vec::new(),
+		})))
+	}
+
+	/// Move the time on by `duration` and wake up whatever is due by then.
+	pub fn advance(&self, duration: Duration) {
+		let mut state = self.0.lock().expect("the clock is not poisoned");
+		state.now += duration;
+
+		let now = state.now;
+		let (due, sleeping) = state.sleeping.drain(..).partition(|(deadline, _)| *deadline <= now);
+		state.sleeping = sleeping;
+		drop(state);
+
+		for (_, wake) in due {
+			let _ = wake.send(());
+		}
+	}
+}
+
+impl Default for TestClock {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Clock for TestClock {
+	fn now(&self) -> Instant {
+		self.0.lock().expect("the clock is not poisoned").now
+	}
+
+	fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+		let mut state = self.0.lock().expect("the clock is not poisoned");
+
+		if duration == Duration::from_secs(0) {
+			return future::ready(()).boxed();
+		}
+
+		let (wake, woken) = oneshot::channel();
+		let deadline = state.now + duration;
+		state.sleeping.push((deadline, wake));
+
+		// A dropped clock never wakes anything up again.
+		woken.then(|res| match res {
+			Ok(()) => future::ready(()).boxed(),
+			Err(_) => future::pending().boxed(),
+		}).boxed()
+	}
+}